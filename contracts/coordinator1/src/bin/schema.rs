@@ -5,17 +5,20 @@ fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
-        query: QueryMsg
+        query: QueryMsg,
+        migrate: MigrateMsg
     }
 
     // generate a valid json
-    // let msg = ExecuteMsg::AddVote { vote: Vote { tx_id: 1, chain_id: 2, success: true } };
+    // let msg = ExecuteMsg::AddVote { vote: Vote { tx_id: 1, chain_id: 2, success: true, digest: None, abort_reason: None } };
     let msg = InstantiateMsg{
         chain_num: 3,
+        admin: None,
+        allowed_ports: None,
     };
     let json = serde_json::to_string(&msg).unwrap();
     println!("{}", json);
-    let msg2 = ExecuteMsg::AddVote { vote: Vote { tx_id: 2, chain_id: 1, success: true } };
+    let msg2 = ExecuteMsg::AddVote { vote: Vote { tx_id: 2, chain_id: 1, success: true, digest: None, abort_reason: None } };
     let json2 = serde_json::to_string(&msg2).unwrap();
     println!("{}", json2);
 }
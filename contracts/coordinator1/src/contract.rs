@@ -1,58 +1,250 @@
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, IbcMsg, IbcTimeout
+    to_json_binary, Binary, Deps, DepsMut, Empty, Env, Event, MessageInfo, Response, StdResult, IbcMsg, IbcTimeout
 };
+use cw2::set_contract_version;
 use crate::error::ContractError;
-use crate::msg::{InstantiateMsg, QueryMsg, ExecuteMsg};
-use crate::state::{CHAIN_NUM, MY_LOGS};
+use crate::msg::{InstantiateMsg, QueryMsg, ExecuteMsg, MigrateMsg};
+use crate::state::{ADMIN, ALLOWED_PORTS, CHAIN_NUM, CLOSED_VOTES_MIGRATION, MY_LOGS, PAUSED};
+use crate::utils;
 
 // pub const CHAIN_NUM: &str = env!("CHAIN_NUM");
+const CONTRACT_NAME: &str = "crates.io:coordinator1";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     MY_LOGS.save(deps.storage, &"start:".to_string())?;
 
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
+    ADMIN.save(deps.storage, &admin)?;
+    PAUSED.save(deps.storage, &false)?;
+    for port_id in msg.allowed_ports.unwrap_or_default() {
+        ALLOWED_PORTS.save(deps.storage, port_id, &Empty {})?;
+    }
     CHAIN_NUM.save(deps.storage, &msg.chain_num)?;
     Ok(Response::new()
     .add_attribute("method", "instantiate")
+    .add_attribute("admin", admin)
     .add_attribute("chain_num", msg.chain_num.to_string()))
 }
 
+/* MIGRATION */
+pub fn migrate(
+    mut deps: DepsMut,
+    env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let previous_version = utils::check_migration(deps.as_ref(), CONTRACT_NAME, CONTRACT_VERSION)?;
+    migration::run(&mut deps, &env, &previous_version)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let mut resp = Response::new()
+    .add_attribute("method", "migrate")
+    .add_attribute("previous_version", previous_version.to_string())
+    .add_attribute("new_version", CONTRACT_VERSION);
+    if let Some(admin) = msg.admin {
+        let admin = deps.api.addr_validate(&admin)?;
+        ADMIN.save(deps.storage, &admin)?;
+        resp = resp.add_attribute("admin", admin);
+    }
+    Ok(resp)
+}
+
+mod migration {
+    use cw_storage_plus::{Bound, Map};
+    use semver::Version;
+
+    use crate::state::{CLOSED_VOTES, Decision, MAX_MIGRATE};
+    use super::exec::participants;
+    use super::*;
+
+    // storage layout of 0.1.0, OPENING_VOTES is unchanged so open votes carry over as is.
+    // Same namespace as CLOSED_VOTES, an unconverted tx fails to load and so cannot be voted on again
+    const CLOSED_VOTES_V0_1: Map<u32, ClosedVote> = Map::new("closed_votes");
+
+    // decisions closed after the migration share the namespace with the legacy outcomes
+    #[derive(serde::Deserialize, serde::Serialize)]
+    #[serde(untagged)]
+    enum ClosedVote {
+        Legacy(bool),
+        Decision(Decision),
+    }
+
+    // bring the storage of an older version up to date, one step per layout change
+    pub fn run(deps: &mut DepsMut, env: &Env, previous: &Version) -> StdResult<()> {
+        if *previous < Version::new(0, 2, 0) {
+            closed_votes_v0_2(deps, env, None)?;
+        }
+        Ok(())
+    }
+
+    // closed votes became decisions, the original height is unknown so the migration block is recorded.
+    // Converts a page per call, MigrateClosedVotes continues after the stored cursor
+    pub fn closed_votes_v0_2(deps: &mut DepsMut, env: &Env, limit: Option<u32>) -> StdResult<(usize, bool)> {
+        let chains = participants(deps.as_ref())?;
+        let start = CLOSED_VOTES_MIGRATION.may_load(deps.storage)?.map(Bound::exclusive);
+        let limit = limit.unwrap_or(MAX_MIGRATE).min(MAX_MIGRATE) as usize;
+        let mut page = CLOSED_VOTES_V0_1
+        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .take(limit + 1)
+        .collect::<StdResult<Vec<(u32, ClosedVote)>>>()?;
+        let remaining = page.len() > limit;
+        page.truncate(limit);
+        for (tx_id, closed) in page.iter() {
+            if let ClosedVote::Legacy(commitment) = *closed {
+                CLOSED_VOTES.save(deps.storage, *tx_id, &Decision{
+                    commitment,
+                    voted_chains: if commitment { chains.clone() } else { Vec::new() },
+                    height: env.block.height,
+                    time: env.block.time,
+                    forced_reason: None,
+                    decision_hash: None,
+                    abort_reason: None,
+                    aborted_chains: Vec::new(),
+                })?;
+            }
+        }
+        match page.last() {
+            Some((tx_id, _)) if remaining => CLOSED_VOTES_MIGRATION.save(deps.storage, tx_id)?,
+            _ => CLOSED_VOTES_MIGRATION.remove(deps.storage),
+        }
+        Ok((page.len(), remaining))
+    }
+}
+
 /* QUERY */
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     use QueryMsg::*;
 
     match msg {
-        OpeningVotes{} => to_json_binary(&query::opening_votes(deps)?),
-        ClosedVotes{} => to_json_binary(&query::closed_votes(deps)?),
+        OpeningVotes{ start_after, limit } => to_json_binary(&query::opening_votes(deps, start_after, limit)?),
+        ClosedVotes{ start_after, limit, committed } => to_json_binary(&query::closed_votes(deps, start_after, limit, committed)?),
         MyLogs{} => to_json_binary(&query::my_logs(deps)?),
+        TxStatus{ tx_id } => to_json_binary(&query::tx_status(deps, tx_id)?),
+        PauseState{} => to_json_binary(&query::pause_state(deps)?),
+        AllowedPorts{} => to_json_binary(&query::allowed_ports(deps)?),
+        Submitters{} => to_json_binary(&query::submitters(deps)?),
+        DecisionLog{ start_after, limit } => to_json_binary(&query::decision_log(deps, start_after, limit)?),
     }
 }
 
 mod query {
-    use crate::{msg::{ClosedVotesResp, MyLogsResp, OpeningVotesResp}, state::{CLOSED_VOTES, OPENING_VOTES}};
+    use cosmwasm_std::Addr;
+    use cw_storage_plus::Bound;
+
+    use crate::{msg::{AllowedPortsResp, ClosedVotesResp, DecisionLogResp, MyLogsResp, OpeningVotesResp, PauseStateResp, SubmittersResp, TxState, TxStatusResp, Vote}, state::{CLOSED_VOTES, DECISION_LOG, DECISION_LOG_HEAD, DEFERRED_VOTES, FAILED_VOTES, OPENING_VOTES, SUBMITTERS, TX_REGISTRATIONS}};
+    use super::exec::participants;
 
     use super::*;
 
-    pub fn opening_votes(deps: Deps) -> StdResult<OpeningVotesResp> {
+    const DEFAULT_LIMIT: u32 = 30;
+    const MAX_LIMIT: u32 = 100;
+
+    pub fn opening_votes(deps: Deps, start_after: Option<u32>, limit: Option<u32>) -> StdResult<OpeningVotesResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
         let votes = OPENING_VOTES
-        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
         .collect::<StdResult<Vec<(u32, Vec<u16>)>>>()?;
 
         Ok(OpeningVotesResp{ votes })
     }
 
-    pub fn closed_votes(deps: Deps) -> StdResult<ClosedVotesResp> {
+    pub fn closed_votes(deps: Deps, start_after: Option<u32>, limit: Option<u32>, committed: Option<bool>) -> StdResult<ClosedVotesResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
         let votes = CLOSED_VOTES
-        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(tx_id, decision)| (tx_id, decision.commitment)))
+        .filter(|item| match (item, committed) {
+            (Ok((_, commitment)), Some(c)) => *commitment == c,
+            _ => true,
+        })
+        .take(limit)
         .collect::<StdResult<Vec<(u32, bool)>>>()?;
 
         Ok(ClosedVotesResp{ votes })
     }
 
+    pub fn tx_status(deps: Deps, tx_id: u32) -> StdResult<TxStatusResp> {
+        let (state, voted_chains, decision) = match CLOSED_VOTES.may_load(deps.storage, tx_id)? {
+            Some(decision) => (TxState::Closed, decision.voted_chains.clone(), Some(decision)),
+            None => match OPENING_VOTES.may_load(deps.storage, tx_id)? {
+                Some(voted_chains) => (TxState::Open, voted_chains, None),
+                None => (TxState::Unknown, Vec::new(), None),
+            },
+        };
+        let failed_chains = FAILED_VOTES.may_load(deps.storage, tx_id)?.unwrap_or_default();
+        let missing_chains = participants(deps)?
+        .into_iter()
+        .filter(|c| !voted_chains.contains(c) && !failed_chains.contains(c))
+        .collect();
+        let registration = TX_REGISTRATIONS.may_load(deps.storage, tx_id)?;
+        Ok(TxStatusResp{
+            tx_id,
+            state,
+            voted_chains,
+            failed_chains,
+            missing_chains,
+            decision: decision.as_ref().map(|d| d.commitment),
+            decided_height: decision.as_ref().map(|d| d.height),
+            decided_time: decision.as_ref().map(|d| d.time),
+            forced_reason: decision.as_ref().and_then(|d| d.forced_reason.clone()),
+            decision_hash: decision.as_ref().and_then(|d| d.decision_hash.clone()),
+            abort_reason: decision.as_ref().and_then(|d| d.abort_reason),
+            aborted_chains: decision.map(|d| d.aborted_chains).unwrap_or_default(),
+            policy: registration.as_ref().map(|r| r.policy.clone()).unwrap_or_default(),
+            expected_digests: registration.map(|r| r.digests).unwrap_or_default(),
+        })
+    }
+
+    pub fn pause_state(deps: Deps) -> StdResult<PauseStateResp> {
+        Ok(PauseStateResp{
+            paused: utils::is_paused(deps)?,
+            admin: ADMIN.may_load(deps.storage)?,
+            deferred_votes: DEFERRED_VOTES
+            .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .take(DEFAULT_LIMIT as usize)
+            .map(|item| item.map(|(_, vote)| vote))
+            .collect::<StdResult<Vec<Vote>>>()?,
+        })
+    }
+
+    pub fn allowed_ports(deps: Deps) -> StdResult<AllowedPortsResp> {
+        let ports = ALLOWED_PORTS
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+        Ok(AllowedPortsResp{ ports })
+    }
+
+    pub fn submitters(deps: Deps) -> StdResult<SubmittersResp> {
+        let submitters = SUBMITTERS
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+        Ok(SubmittersResp{ submitters })
+    }
+
+    pub fn decision_log(deps: Deps, start_after: Option<u32>, limit: Option<u32>) -> StdResult<DecisionLogResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+        let entries = DECISION_LOG
+        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+        Ok(DecisionLogResp{
+            head: DECISION_LOG_HEAD.may_load(deps.storage)?.unwrap_or_default(),
+            entries,
+        })
+    }
+
     pub fn my_logs(deps: Deps) -> StdResult<MyLogsResp> {
         let logs = MY_LOGS.load(deps.storage)?;
         Ok(MyLogsResp{logs})
@@ -63,55 +255,242 @@ mod query {
 pub fn execute(
     mut deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     use ExecuteMsg::*;
 
     match msg {
         AddVote { vote } => {
-            let (attrs, msgs) = exec::add_vote(&mut deps, &env, &vote)?;
-            Ok(Response::new().add_attributes(attrs).add_messages(msgs))
+            // participants vote over ibc, anyone else could forge votes
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+            let (attrs, msgs, events) = exec::add_vote(&mut deps, &env, &vote, None)?;
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attributes(attrs).add_messages(msgs).add_messages(flushed).add_events(events))
+        },
+        Pause {} => exec::pause(deps, &info),
+        Resume {} => {
+            let (attrs, msgs, events) = exec::resume(&mut deps, &env, &info)?;
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attributes(attrs).add_messages(msgs).add_messages(flushed).add_events(events))
+        },
+        ForceDecide { tx_id, commit, reason } => {
+            let (attrs, msgs, events) = exec::force_decide(&mut deps, &env, &info, tx_id, commit, reason)?;
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attributes(attrs).add_messages(msgs).add_messages(flushed).add_events(events))
+        },
+        ReplayDeferred { limit } => {
+            let (attrs, msgs, events) = exec::replay_deferred(&mut deps, &env, limit)?;
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attributes(attrs).add_messages(msgs).add_messages(flushed).add_events(events))
+        },
+        Flush {} => {
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attribute("flushed_packets", flushed.len().to_string()).add_messages(flushed))
         },
+        RegisterTx { tx_id, digests, policy } => exec::register_tx(deps, &info, tx_id, digests, policy.unwrap_or_default()),
+        RequestAbort { tx_id } => {
+            let (attrs, msgs, events) = exec::request_abort(&mut deps, &env, &info, tx_id)?;
+            let flushed = exec::flush(&mut deps, &env)?;
+            Ok(Response::new().add_attributes(attrs).add_messages(msgs).add_messages(flushed).add_events(events))
+        },
+        MigrateClosedVotes { limit } => {
+            // a page is converted at migration, nothing is left without a cursor
+            if CLOSED_VOTES_MIGRATION.may_load(deps.storage)?.is_none() {
+                return Ok(Response::new().add_attribute("method", "migrate_closed_votes").add_attribute("remaining", "false"))
+            }
+            let (converted, remaining) = migration::closed_votes_v0_2(&mut deps, &env, limit)?;
+            Ok(Response::new()
+            .add_attribute("method", "migrate_closed_votes")
+            .add_attribute("converted", converted.to_string())
+            .add_attribute("remaining", remaining.to_string()))
+        },
+        RetireConnection { connection_id } => exec::retire_connection(deps, &info, connection_id),
+        AllowSubmitter { address } => exec::set_submitter_allowed(deps, &info, address, true),
+        DisallowSubmitter { address } => exec::set_submitter_allowed(deps, &info, address, false),
+        AllowPort { port_id } => exec::set_port_allowed(deps, &info, port_id, true),
+        DisallowPort { port_id } => exec::set_port_allowed(deps, &info, port_id, false),
     }
 }
 
 pub mod exec {
-    use crate::msg::Instruction;
+    use crate::msg::{AbortReason, ChainDigest, CommitPolicy, Instruction, Instructions};
     use crate::{error::ContractError, msg::Vote};
-    use crate::state::{CHAIN_NUM, CLOSED_VOTES, MY_CHANNELS, OPENING_VOTES};
+    use crate::state::{BUFFERED_INSTRUCTIONS, CHAIN_NUM, CLOSED_VOTES, DECISION_LOG, DECISION_LOG_HEAD, DEFERRED_VOTES, Decision, DecisionLogEntry, FAILED_VOTES, MAX_REPLAY, MY_CHANNELS, OPENING_VOTES, SUBMITTERS, TX_REGISTRATIONS, TxRegistration};
     use super::*;
 
-    pub fn create_instruction(tx_id: u32, commitment: bool, deps: Deps, env: &Env) -> StdResult<Vec<IbcMsg>>{
-        let my_instruction = Instruction{
-            tx_id,
+    /// attributes, packets and events produced by handlers shared between execute and ibc entry points
+    pub type HandlerResult = (Vec<(String, String)>, Vec<IbcMsg>, Vec<Event>);
+
+    // logic chains are numbered from 1 to CHAIN_NUM
+    pub fn participants(deps: Deps) -> StdResult<Vec<u16>> {
+        Ok((1..=CHAIN_NUM.load(deps.storage)?).collect())
+    }
+
+    // appends the decision to the decision log and returns it for the instruction
+    pub fn close_vote(
+        deps: &mut DepsMut,
+        env: &Env,
+        tx_id: u32,
+        commitment: bool,
+        voted_chains: Vec<u16>,
+        forced_reason: Option<String>,
+        abort_reason: Option<AbortReason>,
+    ) -> StdResult<Decision> {
+        let aborted_chains = match commitment {
+            true => FAILED_VOTES.may_load(deps.storage, tx_id)?.unwrap_or_default(),
+            false => Vec::new(),
+        };
+        let decision_hash = append_decision_log(deps, env, tx_id, commitment, &aborted_chains)?;
+        OPENING_VOTES.remove(deps.storage, tx_id);
+        FAILED_VOTES.remove(deps.storage, tx_id);
+        let decision = Decision{
             commitment,
+            voted_chains,
+            height: env.block.height,
+            time: env.block.time,
+            forced_reason,
+            decision_hash: Some(decision_hash),
+            abort_reason: if commitment { None } else { abort_reason },
+            aborted_chains,
         };
-        let data = to_json_binary(&my_instruction)?;
-        MY_CHANNELS.range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-        .map(|item| {
-            let (_,v) = item?;
-            Ok(IbcMsg::SendPacket {
-                channel_id: v.channel_id,
-                data: data.clone(),
-                timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(3600000)),
-            })
+        CLOSED_VOTES.save(deps.storage, tx_id, &decision)?;
+        Ok(decision)
+    }
+
+    fn append_decision_log(deps: &mut DepsMut, env: &Env, tx_id: u32, commitment: bool, aborted_chains: &[u16]) -> StdResult<String> {
+        let prev = DECISION_LOG_HEAD.may_load(deps.storage)?.unwrap_or_default();
+        let hash = utils::decision_hash(&prev, tx_id, commitment, aborted_chains, env.block.height)?;
+        let position = match DECISION_LOG.keys(deps.storage, None, None, cosmwasm_std::Order::Descending).next() {
+            Some(last) => last? + 1,
+            None => 0,
+        };
+        DECISION_LOG.save(deps.storage, position, &DecisionLogEntry{
+            tx_id,
+            commitment,
+            aborted_chains: aborted_chains.to_vec(),
+            height: env.block.height,
+            hash: hash.clone(),
+        })?;
+        DECISION_LOG_HEAD.save(deps.storage, &hash)?;
+        Ok(hash)
+    }
+
+    // decisions are buffered per connection in use and leave as one Instructions packet once its channel is open
+    pub fn create_instruction(deps: &mut DepsMut, tx_id: u32, decision: &Decision) -> StdResult<()>{
+        let connections = MY_CHANNELS
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, v)) if v.retired => None,
+            Ok((connection_id, _)) => Some(Ok(connection_id)),
+            Err(e) => Some(Err(e)),
         })
-        .collect::<StdResult<Vec<IbcMsg>>>()
+        .collect::<StdResult<Vec<String>>>()?;
+        for connection_id in connections {
+            queue_instruction(deps, &connection_id, tx_id, decision)?;
+        }
+        Ok(())
+    }
+
+    fn queue_instruction(deps: &mut DepsMut, connection_id: &str, tx_id: u32, decision: &Decision) -> StdResult<()> {
+        let mut buffered = BUFFERED_INSTRUCTIONS.may_load(deps.storage, connection_id.to_string())?.unwrap_or_default();
+        if buffered.iter().any(|i| i.tx_id == tx_id) {
+            return Ok(())
+        }
+        buffered.push(Instruction{
+            tx_id,
+            commitment: decision.commitment,
+            decision_hash: decision.decision_hash.clone(),
+            abort_reason: decision.abort_reason,
+            aborted_chains: decision.aborted_chains.clone(),
+        });
+        BUFFERED_INSTRUCTIONS.save(deps.storage, connection_id.to_string(), &buffered)
+    }
+
+    // remember which chain a connection belongs to, the first vote of a participant over it tells
+    pub fn bind_chain(deps: &mut DepsMut, connection_id: &str, chain_id: u16) -> StdResult<()> {
+        if !participants(deps.as_ref())?.contains(&chain_id) {
+            return Ok(())
+        }
+        let mut channel_info = MY_CHANNELS.load(deps.storage, connection_id.to_string())?;
+        if channel_info.chain_id.is_none() {
+            channel_info.chain_id = Some(chain_id);
+            MY_CHANNELS.save(deps.storage, connection_id.to_string(), &channel_info)?;
+        }
+        Ok(())
+    }
+
+    // a chain without a known channel counts as reachable, it may not have voted over ibc yet
+    fn reachable(deps: Deps, chain_id: u16) -> StdResult<bool> {
+        let mut known = false;
+        for item in MY_CHANNELS.range(deps.storage, None, None, cosmwasm_std::Order::Ascending) {
+            let (_, v) = item?;
+            if v.chain_id == Some(chain_id) {
+                if !v.closed && !v.retired {
+                    return Ok(true)
+                }
+                known = true;
+            }
+        }
+        Ok(!known)
     }
 
+    // the connection a packet arrived on
+    pub fn connection_of(deps: Deps, channel_id: &str) -> StdResult<Option<String>> {
+        MY_CHANNELS
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .find_map(|item| match item {
+            Ok((connection_id, v)) if v.channel_id == channel_id => Some(Ok(connection_id)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .transpose()
+    }
+
+    // send the buffer of every connection whose channel is open, the others keep theirs
+    pub fn flush(deps: &mut DepsMut, env: &Env) -> StdResult<Vec<IbcMsg>> {
+        let buffers = BUFFERED_INSTRUCTIONS
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<(String, Vec<Instruction>)>>>()?;
+        let mut msgs: Vec<IbcMsg> = Vec::new();
+        for (connection_id, buffered) in buffers {
+            let channel = match MY_CHANNELS.may_load(deps.storage, connection_id.clone())? {
+                Some(channel) if channel.finalized && !channel.closed && !channel.retired => channel,
+                _ => continue,
+            };
+            msgs.push(IbcMsg::SendPacket {
+                channel_id: channel.channel_id,
+                data: to_json_binary(&Instructions{ items: buffered })?,
+                timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(3600000)),
+            });
+            BUFFERED_INSTRUCTIONS.remove(deps.storage, connection_id);
+        }
+        Ok(msgs)
+    }
+
+    // source is the connection the vote arrived on, None for votes not received over ibc
     pub fn add_vote(
         deps: &mut DepsMut,
         env: &Env,
         new_vote: &Vote,
-    ) -> Result<(Vec<(String, String)>, Vec<IbcMsg>), ContractError> {
-        // pre-check: open and unvoted
+        source: Option<&str>,
+    ) -> Result<HandlerResult, ContractError> {
+        // pre-check: a participant, open and unvoted
+        if !participants(deps.as_ref())?.contains(&new_vote.chain_id) {
+            return Err(ContractError::UnknownChain { chain_id: new_vote.chain_id });
+        }
         let mut voted_chains = match OPENING_VOTES.may_load(deps.storage, new_vote.tx_id)? {
             None=> {
                 // 2 possibilities: 1) uncreated (receive the first vote) 2) has been closed
                 match CLOSED_VOTES.may_load(deps.storage, new_vote.tx_id)? {
                     None=> Vec::new(),
-                    Some(_)=>{
+                    Some(decision)=>{
+                        // a vote resent after a rebind, the participant may have missed the decision
+                        if let Some(connection_id) = source {
+                            queue_instruction(deps, connection_id, new_vote.tx_id, &decision)?;
+                            let attrs = vec![("resent_decision".to_string(), new_vote.tx_id.to_string())];
+                            return Ok((attrs, Vec::new(), Vec::new()))
+                        }
                         return Err(ContractError::AlreadyClosed { tx_id: new_vote.tx_id });
                     },
                 }
@@ -119,35 +498,316 @@ pub mod exec {
             },
             Some(voted_chains) => voted_chains,
         };
-        if voted_chains.contains(&new_vote.chain_id){
+        let mut failed_chains = FAILED_VOTES.may_load(deps.storage, new_vote.tx_id)?.unwrap_or_default();
+        if voted_chains.contains(&new_vote.chain_id) || failed_chains.contains(&new_vote.chain_id) {
             return Err(ContractError::AlreadyVoted { tx_id: new_vote.tx_id, chain_id: new_vote.chain_id })
         }
 
-        // add vote
+        // the vote would open a new tx, keep it until resumed since the sender will not resend it
         let mut attrs: Vec<(String, String)> = vec![("new_vote".to_string(), format!("{:?}", new_vote))];
-        let mut msgs: Vec<IbcMsg> = Vec::new();
-        match new_vote.success {
-            false => {
-                // fast abort
-                OPENING_VOTES.remove(deps.storage, new_vote.tx_id);
-                CLOSED_VOTES.save(deps.storage, new_vote.tx_id, &false)?;
-                msgs.extend(create_instruction(new_vote.tx_id, false, deps.as_ref(), env)?);
+        if voted_chains.is_empty() && failed_chains.is_empty() && utils::is_paused(deps.as_ref())? {
+            DEFERRED_VOTES.save(deps.storage, (new_vote.tx_id, new_vote.chain_id), new_vote)?;
+            let event = utils::paused_rejection_event("add_vote")
+            .add_attribute("tx_id", new_vote.tx_id.to_string())
+            .add_attribute("chain_id", new_vote.chain_id.to_string());
+            return Ok((attrs, Vec::new(), vec![event]))
+        }
+
+        // a vote for different content than registered aborts the tx whatever it says
+        if let Some(expected) = expected_digest(deps.as_ref(), new_vote.tx_id, new_vote.chain_id)? {
+            if new_vote.digest.as_ref() != Some(&expected) {
+                let decision = close_vote(deps, env, new_vote.tx_id, false, voted_chains, None, Some(AbortReason::DigestMismatch))?;
+                create_instruction(deps, new_vote.tx_id, &decision)?;
                 attrs.push(("closed_vote".to_string(), "aborted".to_string()));
+                let event = Event::new("safety_violation")
+                .add_attribute("tx_id", new_vote.tx_id.to_string())
+                .add_attribute("chain_id", new_vote.chain_id.to_string())
+                .add_attribute("expected_digest", expected)
+                .add_attribute("digest", new_vote.digest.clone().unwrap_or_default());
+                return Ok((attrs, Vec::new(), vec![event]))
             }
-            true => {
-                voted_chains.push(new_vote.chain_id);
-                let chain_num = CHAIN_NUM.load(deps.storage)?;
-                if (voted_chains.len() as u16) == chain_num{
-                    // instruct to commit
-                    OPENING_VOTES.remove(deps.storage, new_vote.tx_id);
-                    CLOSED_VOTES.save(deps.storage, new_vote.tx_id, &true)?;
-                    msgs.extend(create_instruction(new_vote.tx_id, true, deps.as_ref(), env)?);
-                    attrs.push(("closed_vote".to_string(), "committed".to_string()));
-                } else {
-                    OPENING_VOTES.save(deps.storage, new_vote.tx_id, &voted_chains)?;
+        }
+
+        // add vote, a decision is buffered until the caller flushes
+        match new_vote.success {
+            true => voted_chains.push(new_vote.chain_id),
+            false => failed_chains.push(new_vote.chain_id),
+        }
+        let policy = TX_REGISTRATIONS.may_load(deps.storage, new_vote.tx_id)?.map(|r| r.policy).unwrap_or_default();
+        match decide(&policy, CHAIN_NUM.load(deps.storage)?, &voted_chains, &failed_chains) {
+            Some(commitment) => {
+                // the failed chains of a committed tx are kept with the decision
+                FAILED_VOTES.save(deps.storage, new_vote.tx_id, &failed_chains)?;
+                let decision = close_vote(deps, env, new_vote.tx_id, commitment, voted_chains, None, new_vote.abort_reason)?;
+                create_instruction(deps, new_vote.tx_id, &decision)?;
+                attrs.push(("closed_vote".to_string(), if commitment { "committed" } else { "aborted" }.to_string()));
+            },
+            None => {
+                OPENING_VOTES.save(deps.storage, new_vote.tx_id, &voted_chains)?;
+                if !failed_chains.is_empty() {
+                    FAILED_VOTES.save(deps.storage, new_vote.tx_id, &failed_chains)?;
                 }
+            },
+        }
+        Ok((attrs, Vec::new(), Vec::new()))
+    }
+
+    // Some(commitment) once the policy settles the tx, a commit waits for every chain so each learns its part
+    fn decide(policy: &CommitPolicy, chain_num: u16, succeeded: &[u16], failed: &[u16]) -> Option<bool> {
+        let unvoted = chain_num.saturating_sub((succeeded.len() + failed.len()) as u16);
+        let lost = match policy {
+            CommitPolicy::Unanimous => !failed.is_empty(),
+            CommitPolicy::RequiredSet { required } => failed.iter().any(|c| required.contains(c)),
+            CommitPolicy::Quorum { k } => (succeeded.len() as u16) + unvoted < *k,
+        };
+        match (lost, unvoted) {
+            (true, _) => Some(false),
+            (false, 0) => Some(true),
+            _ => None,
+        }
+    }
+
+    fn expected_digest(deps: Deps, tx_id: u32, chain_id: u16) -> StdResult<Option<String>> {
+        Ok(TX_REGISTRATIONS.may_load(deps.storage, tx_id)?.and_then(|r| {
+            r.digests.into_iter().find(|d| d.chain_id == chain_id).map(|d| d.digest)
+        }))
+    }
+
+    pub fn register_tx(
+        deps: DepsMut,
+        info: &MessageInfo,
+        tx_id: u32,
+        digests: Vec<ChainDigest>,
+        policy: CommitPolicy,
+    ) -> Result<Response, ContractError> {
+        // first registration wins, so only trusted senders may set the digests,
+        // a policy that commits without every chain can create value and is left to the admin
+        utils::ensure_submitter(deps.as_ref(), &info.sender)?;
+        if policy != CommitPolicy::Unanimous {
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        }
+        if TX_REGISTRATIONS.has(deps.storage, tx_id)
+        || OPENING_VOTES.has(deps.storage, tx_id)
+        || CLOSED_VOTES.has(deps.storage, tx_id) {
+            return Err(ContractError::RegistrationClosed { tx_id });
+        }
+        let chains = participants(deps.as_ref())?;
+        if let Some(d) = digests.iter().find(|d| !chains.contains(&d.chain_id)) {
+            return Err(ContractError::UnknownChain { chain_id: d.chain_id });
+        }
+        match &policy {
+            CommitPolicy::Unanimous => {},
+            CommitPolicy::RequiredSet { required } => {
+                if let Some(chain_id) = required.iter().find(|c| !chains.contains(c)) {
+                    return Err(ContractError::UnknownChain { chain_id: *chain_id });
+                }
+            },
+            CommitPolicy::Quorum { k } => {
+                let chain_num = chains.len() as u16;
+                if *k == 0 || *k > chain_num {
+                    return Err(ContractError::InvalidQuorum { k: *k, chain_num });
+                }
+            },
+        }
+        TX_REGISTRATIONS.save(deps.storage, tx_id, &TxRegistration{
+            submitter: info.sender.clone(),
+            digests,
+            policy: policy.clone(),
+        })?;
+        Ok(Response::new()
+        .add_attribute("method", "register_tx")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("policy", format!("{:?}", policy))
+        .add_attribute("submitter", info.sender.clone()))
+    }
+
+    pub fn request_abort(
+        deps: &mut DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        tx_id: u32,
+    ) -> Result<HandlerResult, ContractError> {
+        // the registrant is trusted only while it is still an allowed submitter
+        let submitter = TX_REGISTRATIONS.may_load(deps.storage, tx_id)?.map(|r| r.submitter);
+        if submitter.as_ref() != Some(&info.sender) || !SUBMITTERS.has(deps.storage, &info.sender) {
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        }
+        let voted_chains = match OPENING_VOTES.may_load(deps.storage, tx_id)? {
+            Some(voted_chains) => voted_chains,
+            None => return Err(ContractError::NotOpen { tx_id }),
+        };
+
+        let decision = close_vote(deps, env, tx_id, false, voted_chains.clone(), None, Some(AbortReason::Requested))?;
+        create_instruction(deps, tx_id, &decision)?;
+
+        let attrs = vec![("closed_vote".to_string(), "aborted".to_string())];
+        let event = Event::new("requested_abort")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("voted_chains", format!("{:?}", voted_chains));
+        Ok((attrs, Vec::new(), vec![event]))
+    }
+
+    pub fn force_decide(
+        deps: &mut DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        tx_id: u32,
+        commit: bool,
+        reason: String,
+    ) -> Result<HandlerResult, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        if CLOSED_VOTES.has(deps.storage, tx_id) {
+            return Err(ContractError::AlreadyClosed { tx_id });
+        }
+        let voted_chains = OPENING_VOTES.may_load(deps.storage, tx_id)?.ok_or(ContractError::NotOpen { tx_id })?;
+        // a participant that can still vote must not be committed over, the lost ones abort their own part
+        // since the tx may fail on them once they come back and apply the decision
+        if commit {
+            let missing = participants(deps.as_ref())?
+            .into_iter()
+            .filter(|c| !voted_chains.contains(c))
+            .collect::<Vec<u16>>();
+            for &chain_id in missing.iter() {
+                if reachable(deps.as_ref(), chain_id)? {
+                    return Err(ContractError::ReachableWithoutSuccess { tx_id, chain_id })
+                }
+            }
+            FAILED_VOTES.save(deps.storage, tx_id, &missing)?;
+        }
+        let decision = close_vote(deps, env, tx_id, commit, voted_chains.clone(), Some(reason.clone()), Some(AbortReason::Forced))?;
+        create_instruction(deps, tx_id, &decision)?;
+
+        let attrs = vec![("closed_vote".to_string(), if commit { "committed" } else { "aborted" }.to_string())];
+        let event = Event::new("forced_decision")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("commitment", commit.to_string())
+        .add_attribute("admin", info.sender.clone())
+        .add_attribute("voted_chains", format!("{:?}", voted_chains))
+        .add_attribute("reason", reason);
+        Ok((attrs, Vec::new(), vec![event]))
+    }
+
+    pub fn retire_connection(
+        deps: DepsMut,
+        info: &MessageInfo,
+        connection_id: String,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        let mut channel_info = MY_CHANNELS.load(deps.storage, connection_id.clone())?;
+        channel_info.retired = true;
+        MY_CHANNELS.save(deps.storage, connection_id.clone(), &channel_info)?;
+        BUFFERED_INSTRUCTIONS.remove(deps.storage, connection_id.clone());
+        Ok(Response::new()
+        .add_attribute("method", "retire_connection")
+        .add_attribute("connection_id", connection_id))
+    }
+
+    pub fn set_submitter_allowed(
+        deps: DepsMut,
+        info: &MessageInfo,
+        address: String,
+        allowed: bool,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        let address = deps.api.addr_validate(&address)?;
+        if allowed {
+            SUBMITTERS.save(deps.storage, &address, &Empty {})?;
+        } else {
+            SUBMITTERS.remove(deps.storage, &address);
+        }
+        Ok(Response::new()
+        .add_attribute("method", if allowed { "allow_submitter" } else { "disallow_submitter" })
+        .add_attribute("submitter", address))
+    }
+
+    pub fn set_port_allowed(
+        deps: DepsMut,
+        info: &MessageInfo,
+        port_id: String,
+        allowed: bool,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        if allowed {
+            ALLOWED_PORTS.save(deps.storage, port_id.clone(), &Empty {})?;
+        } else {
+            ALLOWED_PORTS.remove(deps.storage, port_id.clone());
+        }
+        Ok(Response::new()
+        .add_attribute("method", if allowed { "allow_port" } else { "disallow_port" })
+        .add_attribute("port_id", port_id))
+    }
+
+    pub fn pause(deps: DepsMut, info: &MessageInfo) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        PAUSED.save(deps.storage, &true)?;
+        Ok(Response::new().add_event(Event::new("paused").add_attribute("admin", info.sender.clone())))
+    }
+
+    pub fn resume(
+        deps: &mut DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+    ) -> Result<HandlerResult, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        PAUSED.save(deps.storage, &false)?;
+
+        let (attrs, msgs, mut events) = replay_deferred(deps, env, None)?;
+        events.insert(0, Event::new("resumed").add_attribute("admin", info.sender.clone()));
+        Ok((attrs, msgs, events))
+    }
+
+    // replay deferred votes in tx order, a vote that no longer applies is only logged
+    pub fn replay_deferred(
+        deps: &mut DepsMut,
+        env: &Env,
+        limit: Option<u32>,
+    ) -> Result<HandlerResult, ContractError> {
+        if utils::is_paused(deps.as_ref())? {
+            return Ok((Vec::new(), Vec::new(), vec![utils::paused_rejection_event("replay_deferred")]))
+        }
+        let limit = limit.unwrap_or(MAX_REPLAY).min(MAX_REPLAY) as usize;
+        let page = DEFERRED_VOTES
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<((u32, u16), Vote)>>>()?;
+        let mut deferred: Vec<Vote> = Vec::new();
+        for (key, vote) in page {
+            DEFERRED_VOTES.remove(deps.storage, key);
+            deferred.push(vote);
+        }
+        let remaining = DEFERRED_VOTES.keys(deps.storage, None, None, cosmwasm_std::Order::Ascending).next().is_some();
+        let (attrs, msgs, mut events) = add_votes(deps, env, &deferred, None)?;
+        events.insert(0, Event::new("deferred_votes_replayed")
+            .add_attribute("replayed_votes", deferred.len().to_string())
+            .add_attribute("remaining", remaining.to_string()));
+        Ok((attrs, msgs, events))
+    }
+
+    // apply votes in order, a vote that does not apply is only logged so the others still count
+    pub fn add_votes(
+        deps: &mut DepsMut,
+        env: &Env,
+        votes: &[Vote],
+        source: Option<&str>,
+    ) -> StdResult<HandlerResult> {
+        let mut attrs: Vec<(String, String)> = Vec::new();
+        let mut msgs: Vec<IbcMsg> = Vec::new();
+        let mut events: Vec<Event> = Vec::new();
+        for vote in votes.iter() {
+            match add_vote(deps, env, vote, source) {
+                Ok((a, m, e)) => {
+                    attrs.extend(a);
+                    msgs.extend(m);
+                    events.extend(e);
+                },
+                Err(e) => {
+                    let mut logs = MY_LOGS.load(deps.storage)?;
+                    logs.push_str(format!("\n{:?}", e).as_str());
+                    MY_LOGS.save(deps.storage, &logs)?;
+                },
             }
         }
-        Ok((attrs, msgs))
+        Ok((attrs, msgs, events))
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Addr, StdError};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -16,4 +16,59 @@ pub enum ContractError {
     AlreadyClosed {
         tx_id: u32,
     },
-}
\ No newline at end of file
+
+    #[error("tx {tx_id} can no longer be registered, it is already registered or has votes")]
+    RegistrationClosed {
+        tx_id: u32,
+    },
+
+    #[error("vote for tx {tx_id} is not open")]
+    NotOpen {
+        tx_id: u32,
+    },
+
+    #[error("quorum {k} is not between 1 and the {chain_num} participants")]
+    InvalidQuorum {
+        k: u16,
+        chain_num: u16,
+    },
+
+    #[error("chain {chain_id} is still reachable and has not voted success on tx {tx_id}")]
+    ReachableWithoutSuccess {
+        tx_id: u32,
+        chain_id: u16,
+    },
+
+    #[error("chain {chain_id} is not a participant")]
+    UnknownChain {
+        chain_id: u16,
+    },
+
+    #[error("expect ibc channel version {expected} but got {version}")]
+    InvalidIbcVersion {
+        version: String,
+        expected: String,
+    },
+
+    #[error("only unordered ibc channels are supported")]
+    InvalidIbcOrder {},
+
+    #[error("counterparty port {port_id} is not an allowed participant port")]
+    InvalidCounterpartyPort {
+        port_id: String,
+    },
+
+    #[error("{sender} is not contract admin")]
+    Unauthorized { sender: Addr },
+
+    #[error("cannot migrate from contract {previous_contract}")]
+    InvalidMigrationContract {
+        previous_contract: String,
+    },
+
+    #[error("cannot migrate from version {previous_version} to older version {new_version}")]
+    InvalidMigrationVersion {
+        previous_version: String,
+        new_version: String,
+    },
+}
@@ -1,25 +1,51 @@
 use cosmwasm_std::{ensure, entry_point, DepsMut, Env, StdError, StdResult, from_json};
-use cosmwasm_std::{IbcBasicResponse, IbcChannelConnectMsg, IbcChannelCloseMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketReceiveMsg, IbcReceiveResponse, IbcPacketAckMsg, IbcPacketTimeoutMsg, StdAck, IbcAcknowledgement};
+use cosmwasm_std::{Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel, IbcChannelConnectMsg, IbcChannelCloseMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketReceiveMsg, IbcReceiveResponse, IbcPacketAckMsg, IbcPacketTimeoutMsg, StdAck};
 
-use crate::msg::Vote;
-use crate::state::{ChannelInfo, MY_CHANNELS, MY_LOGS};
-use crate::contract::exec::add_vote;
+use crate::msg::{Vote, Votes};
+use crate::state::{ChannelInfo, ALLOWED_PORTS, MY_CHANNELS, MY_LOGS};
+use crate::contract::exec::{add_vote, add_votes, bind_chain, connection_of, flush};
 use crate::error::ContractError;
 
+pub const IBC_APP_VERSION: &str = "myfc-1";
+pub const IBC_ORDERING: IbcOrder = IbcOrder::Unordered;
+
+// check a channel against the protocol and the allowed participant ports
+fn validate_channel(deps: &DepsMut, channel: &IbcChannel, counterparty_version: Option<&str>) -> Result<(), ContractError> {
+    if channel.order != IBC_ORDERING {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    for version in std::iter::once(channel.version.as_str()).chain(counterparty_version) {
+        if version != IBC_APP_VERSION {
+            return Err(ContractError::InvalidIbcVersion { version: version.to_string(), expected: IBC_APP_VERSION.to_string() });
+        }
+    }
+    let port_id = &channel.counterparty_endpoint.port_id;
+    if !ALLOWED_PORTS.has(deps.storage, port_id.clone()) {
+        return Err(ContractError::InvalidCounterpartyPort { port_id: port_id.clone() });
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_open(
     deps: DepsMut,
     _env: Env,
     msg: IbcChannelOpenMsg
-) -> StdResult<IbcChannelOpenResponse> {
+) -> Result<IbcChannelOpenResponse, ContractError> {
     // use connection_id to differentiate chains
     let channel = msg.channel();
-    ensure!(MY_CHANNELS.may_load(deps.storage, channel.connection_id.clone())?.is_none(), StdError::generic_err("connection already exists"));
+    validate_channel(&deps, channel, msg.counterparty_version())?;
+    // a closed or retired channel can be replaced, so participants are able to rebind
+    let existing = MY_CHANNELS.may_load(deps.storage, channel.connection_id.clone())?;
+    ensure!(existing.map_or(true, |c| c.closed || c.retired), StdError::generic_err("connection already exists"));
     MY_CHANNELS.save(deps.storage, channel.connection_id.clone(), &ChannelInfo{
         channel_id: channel.endpoint.channel_id.clone(),
         finalized: false,
+        closed: false,
+        retired: false,
+        chain_id: None,
     })?;
-    Ok(())
+    Ok(Some(Ibc3ChannelOpenResponse{ version: IBC_APP_VERSION.to_string() }))
 }
  
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -27,11 +53,12 @@ pub fn ibc_channel_connect(
     deps: DepsMut,
     _env: Env,
     msg: IbcChannelConnectMsg,
-) -> StdResult<IbcBasicResponse> {
+) -> Result<IbcBasicResponse, ContractError> {
     let channel = msg.channel();
+    validate_channel(&deps, channel, msg.counterparty_version())?;
     let mut channel_info = MY_CHANNELS.load(deps.storage, channel.connection_id.clone())?;
     ensure!(channel_info.channel_id==channel.endpoint.channel_id, StdError::generic_err("incosistent channel id"));
-    ensure!(channel_info.finalized==false, StdError::generic_err("channel already established"));
+    ensure!(!channel_info.finalized, StdError::generic_err("channel already established"));
     channel_info.finalized=true;
     MY_CHANNELS.save(deps.storage, channel.connection_id.clone(), &channel_info)?;
 
@@ -41,11 +68,20 @@ pub fn ibc_channel_connect(
  
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_close(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: IbcChannelCloseMsg,
+    msg: IbcChannelCloseMsg,
 ) -> StdResult<IbcBasicResponse> {
-    Err(StdError::generic_err("closing not allowed"))
+    // instructions are no longer sent on a closed channel
+    let channel = msg.channel();
+    if let Some(mut channel_info) = MY_CHANNELS.may_load(deps.storage, channel.connection_id.clone())? {
+        if channel_info.channel_id == channel.endpoint.channel_id {
+            channel_info.closed = true;
+            MY_CHANNELS.save(deps.storage, channel.connection_id.clone(), &channel_info)?;
+        }
+    }
+    Ok(IbcBasicResponse::new()
+    .add_attribute("closed_connection_id", channel.connection_id.clone()))
 }
 
 
@@ -73,11 +109,23 @@ pub fn packet_receive_handler(
     env: Env,
     msg: IbcPacketReceiveMsg,        
 ) -> Result<IbcReceiveResponse, ContractError> {
-    let new_vote: Vote = from_json(&msg.packet.data)?;
-    let (attrs, msgs) = add_vote(deps, &env, &new_vote)?;
+    // a bare vote, or a batch from ExecuteBatch, a vote for a closed tx gets the decision again
+    let source = connection_of(deps.as_ref(), &msg.packet.dest.channel_id)?;
+    let votes = match from_json::<Vote>(&msg.packet.data) {
+        Ok(new_vote) => vec![new_vote],
+        Err(_) => from_json::<Votes>(&msg.packet.data)?.items,
+    };
+    if let (Some(connection_id), Some(vote)) = (&source, votes.first()) {
+        bind_chain(deps, connection_id, vote.chain_id)?;
+    }
+    let (attrs, mut msgs, events) = match votes.as_slice() {
+        [new_vote] => add_vote(deps, &env, new_vote, source.as_deref())?,
+        _ => add_votes(deps, &env, &votes, source.as_deref())?,
+    };
+    msgs.extend(flush(deps, &env)?);
 
     let ack = StdAck::success(b"coordinator_success");
-    Ok(IbcReceiveResponse::new().add_attributes(attrs).add_messages(msgs).set_ack(ack))
+    Ok(IbcReceiveResponse::new().add_attributes(attrs).add_messages(msgs).add_events(events).set_ack(ack))
 }
 
 #[entry_point]
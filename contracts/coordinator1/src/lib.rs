@@ -1,15 +1,19 @@
 use cosmwasm_std::{
     entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult
 };
-use msg::{InstantiateMsg, QueryMsg, ExecuteMsg};
+use msg::{InstantiateMsg, QueryMsg, ExecuteMsg, MigrateMsg};
 use error::ContractError;
 
 pub mod contract;
 pub mod msg;
 pub mod state;
 pub mod error;
+pub mod utils;
 pub mod ibc;
 
+#[cfg(test)]
+mod tests;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -34,4 +38,13 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     contract::execute(deps, env, info, msg)
-}
\ No newline at end of file
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    contract::migrate(deps, env, msg)
+}
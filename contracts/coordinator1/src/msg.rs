@@ -1,15 +1,101 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Timestamp};
+
+use crate::state::DecisionLogEntry;
 
 /* Initiate */
 #[cw_serde]
 pub struct InstantiateMsg {
     pub chain_num: u16,
+    /// defaults to the instantiator
+    pub admin: Option<String>,
+    /// ibc ports of the participant contracts, channels from other ports are refused
+    pub allowed_ports: Option<Vec<String>>,
+}
+
+/* Migrate */
+#[cw_serde]
+pub struct MigrateMsg {
+    /// sets the admin, instances from before 0.2.0 have none
+    pub admin: Option<String>,
 }
 
 /* Execute */
 #[cw_serde]
 pub enum ExecuteMsg {
+    /// admin only, participants vote over ibc
     AddVote { vote: Vote },
+    /// admin only, stop opening new votes until resumed
+    Pause {},
+    /// admin only, replays the first page of votes deferred while paused
+    Resume {},
+    /// anyone, replay further deferred votes once resumed
+    ReplayDeferred { limit: Option<u32> },
+    /// admin only, close an open vote that can no longer resolve, e.g. when a participant chain is lost.
+    /// A commit needs a success vote from every participant whose channel is neither closed nor retired,
+    /// the participants without one abort their own part
+    ForceDecide { tx_id: u32, commit: bool, reason: String },
+    /// admin only, accept channel handshakes from a participant port
+    AllowPort { port_id: String },
+    /// admin only
+    DisallowPort { port_id: String },
+    /// admin only, let an address register txs
+    AllowSubmitter { address: String },
+    /// admin only
+    DisallowSubmitter { address: String },
+    /// admin only, stop queueing decisions for a connection that cannot relay anymore
+    RetireConnection { connection_id: String },
+    /// anyone, send buffered decisions, e.g. once a channel is open again
+    Flush {},
+    /// admin or allowed submitter, digests the participants must vote with and the commit policy,
+    /// accepted until the first vote arrives, only the admin may set a policy other than Unanimous
+    RegisterTx { tx_id: u32, digests: Vec<ChainDigest>, policy: Option<CommitPolicy> },
+    /// admin, or the allowed submitter that registered the tx, abort a tx whose vote is open
+    RequestAbort { tx_id: u32 },
+    /// anyone, convert further closed votes of a 0.1.0 instance after migrating
+    MigrateClosedVotes { limit: Option<u32> },
+}
+
+/// when a tx commits, chains that voted failure on a committed tx abort only their own part
+#[cw_serde]
+#[derive(Default)]
+pub enum CommitPolicy {
+    /// every chain must vote success
+    #[default]
+    Unanimous,
+    /// the listed chains must vote success, the others are optional
+    RequiredSet { required: Vec<u16> },
+    /// at least k chains must vote success
+    Quorum { k: u16 },
+}
+
+/// digest of the FcrossTx a participant chain executes, see the mf1 TxDigest query
+#[cw_serde]
+pub struct ChainDigest {
+    pub chain_id: u16,
+    pub digest: String,
+}
+
+/// why a tx aborts, carried by failure votes and abort decisions
+#[cw_serde]
+#[derive(Copy)]
+pub enum AbortReason {
+    /// a debit exceeds the balance
+    InsufficientBalance,
+    /// a credit exceeds the maximum amount
+    Overflow,
+    /// a compare, floor, cap or range condition of the operation does not hold
+    PreconditionFailed,
+    /// the custom operation contract returned no balances
+    CustomRejected,
+    /// the branch had already failed before the tx
+    PriorFailure,
+    /// a vote digest differs from the registered one
+    DigestMismatch,
+    /// an admin forced the abort
+    Forced,
+    /// the submitter or an admin requested the abort while the vote was open
+    Requested,
 }
 
 #[cw_serde]
@@ -17,6 +103,16 @@ pub struct Vote{
     pub tx_id: u32,
     pub chain_id: u16,
     pub success: bool,
+    /// digest of the executed FcrossTx, compared against the registered one
+    pub digest: Option<String>,
+    /// set on failure votes
+    pub abort_reason: Option<AbortReason>,
+}
+
+/// several votes of one participant in a single packet
+#[cw_serde]
+pub struct Votes{
+    pub items: Vec<Vote>,
 }
 
 /* Query */
@@ -24,11 +120,24 @@ pub struct Vote{
 #[derive(QueryResponses)]
 pub enum QueryMsg {
     #[returns(OpeningVotesResp)]
-    OpeningVotes {},
+    OpeningVotes { start_after: Option<u32>, limit: Option<u32> },
+    /// committed filters by outcome when set
     #[returns(ClosedVotesResp)]
-    ClosedVotes {},
+    ClosedVotes { start_after: Option<u32>, limit: Option<u32>, committed: Option<bool> },
     #[returns(MyLogsResp)]
     MyLogs{},
+    #[returns(TxStatusResp)]
+    TxStatus { tx_id: u32 },
+    #[returns(PauseStateResp)]
+    PauseState {},
+    #[returns(AllowedPortsResp)]
+    AllowedPorts {},
+    /// addresses allowed to register txs besides the admin
+    #[returns(SubmittersResp)]
+    Submitters {},
+    /// decisions in closing order, each hash links to the one before
+    #[returns(DecisionLogResp)]
+    DecisionLog { start_after: Option<u32>, limit: Option<u32> },
 }
 
 #[cw_serde]
@@ -41,6 +150,65 @@ pub struct ClosedVotesResp {
     pub votes: Vec<(u32, bool)>,
 }
 
+#[cw_serde]
+pub struct TxStatusResp {
+    pub tx_id: u32,
+    pub state: TxState,
+    pub voted_chains: Vec<u16>,
+    /// chains that voted failure while the vote is open
+    pub failed_chains: Vec<u16>,
+    pub missing_chains: Vec<u16>,
+    /// true for commit, None while the vote is open
+    pub decision: Option<bool>,
+    pub decided_height: Option<u64>,
+    pub decided_time: Option<Timestamp>,
+    /// set when an admin forced the decision
+    pub forced_reason: Option<String>,
+    /// decision log hash after this decision
+    pub decision_hash: Option<String>,
+    /// set on abort decisions
+    pub abort_reason: Option<AbortReason>,
+    /// chains told to abort their part of a committed tx
+    pub aborted_chains: Vec<u16>,
+    pub policy: CommitPolicy,
+    /// registered with RegisterTx, empty if the tx is unregistered
+    pub expected_digests: Vec<ChainDigest>,
+}
+
+#[cw_serde]
+pub enum TxState {
+    /// no vote received yet
+    Unknown,
+    Open,
+    Closed,
+}
+
+#[cw_serde]
+pub struct PauseStateResp {
+    pub paused: bool,
+    pub admin: Option<Addr>,
+    /// first page of the votes deferred while paused, in replay order
+    pub deferred_votes: Vec<Vote>,
+}
+
+#[cw_serde]
+pub struct AllowedPortsResp {
+    pub ports: Vec<String>,
+}
+
+#[cw_serde]
+pub struct SubmittersResp {
+    pub submitters: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct DecisionLogResp {
+    /// hash of the last entry, empty before the first decision
+    pub head: String,
+    /// (position, entry)
+    pub entries: Vec<(u32, DecisionLogEntry)>,
+}
+
 #[cw_serde]
 pub struct MyLogsResp {
     pub logs: String,
@@ -50,4 +218,15 @@ pub struct MyLogsResp {
 pub struct Instruction{
     pub tx_id: u32,
     pub commitment: bool,
-}
\ No newline at end of file
+    /// decision log hash after this decision, see QueryMsg::DecisionLog, None for decisions from before the log
+    pub decision_hash: Option<String>,
+    /// set on abort decisions
+    pub abort_reason: Option<AbortReason>,
+    /// chains that abort their own part of a committed tx
+    pub aborted_chains: Vec<u16>,
+}
+/// decisions of one flush sent as a single packet, applied in order
+#[cw_serde]
+pub struct Instructions{
+    pub items: Vec<Instruction>,
+}
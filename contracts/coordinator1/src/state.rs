@@ -1,24 +1,95 @@
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Empty, Timestamp};
 use cw_storage_plus::{Map, Item};
 
+use crate::msg::{AbortReason, ChainDigest, CommitPolicy, Instruction, Vote};
+
 // CHAIN_NUM="my_secret_key" cargo build
 // pub static CHAIN_NUM: usize = env!("CHAIN_NUM").parse().unwrap();
 // pub const CHAIN_NUM: usize = 3;
 
 pub const OPENING_VOTES: Map<u32, Vec<u16>> = Map::new("opening_votes");
-// bool: success?
-pub const CLOSED_VOTES: Map<u32, bool> = Map::new("closed_votes");
+pub const CLOSED_VOTES: Map<u32, Decision> = Map::new("closed_votes");
+pub const CLOSED_VOTES_MIGRATION: Item<u32> = Item::new("closed_votes_migration"); // last tx id converted from the 0.1.0 layout, absent once done
+pub const MAX_MIGRATE: u32 = 100; // 0.1.0 closed votes converted per call
+pub const FAILED_VOTES: Map<u32, Vec<u16>> = Map::new("failed_votes"); // chains that voted failure on an open tx, only kept under a non-unanimous policy
+pub const BUFFERED_INSTRUCTIONS: Map<String, Vec<Instruction>> = Map::new("buffered_instruction_map"); // decisions not yet sent by connection_id, in decision order
+
+pub const TX_REGISTRATIONS: Map<u32, TxRegistration> = Map::new("tx_registrations");
+
+#[cw_serde]
+pub struct TxRegistration {
+    pub submitter: Addr,
+    /// a vote of a listed chain must carry its digest, otherwise the tx aborts
+    pub digests: Vec<ChainDigest>,
+    #[serde(default)]
+    pub policy: CommitPolicy,
+}
+
+#[cw_serde]
+pub struct Decision {
+    /// true for commit
+    pub commitment: bool,
+    /// chains that had voted success when the vote closed
+    pub voted_chains: Vec<u16>,
+    pub height: u64,
+    pub time: Timestamp,
+    /// set when an admin forced the decision
+    pub forced_reason: Option<String>,
+    /// decision log hash after this decision, None for decisions from before the log
+    #[serde(default)]
+    pub decision_hash: Option<String>,
+    /// set on abort decisions, from the failure vote or the coordinator
+    #[serde(default)]
+    pub abort_reason: Option<AbortReason>,
+    /// chains that voted failure on a committed tx and abort their own part
+    #[serde(default)]
+    pub aborted_chains: Vec<u16>,
+}
+
+// every closed vote in closing order, keyed by position
+pub const DECISION_LOG: Map<u32, DecisionLogEntry> = Map::new("decision_log");
+pub const DECISION_LOG_HEAD: Item<String> = Item::new("decision_log_head"); // hash of the last entry, empty before the first
+
+#[cw_serde]
+pub struct DecisionLogEntry {
+    pub tx_id: u32,
+    pub commitment: bool,
+    /// chains that abort their own part of a committed tx
+    #[serde(default)]
+    pub aborted_chains: Vec<u16>,
+    pub height: u64,
+    /// decision_hash(prev, tx_id, commitment, aborted_chains, height) with prev the hash of the entry before
+    pub hash: String,
+}
 
 pub const CHAIN_NUM: Item<u16> = Item::new("chain_num");
 
+// operator controls
+pub const ADMIN: Item<Addr> = Item::new("admin");
+pub const PAUSED: Item<bool> = Item::new("paused"); // stops opening new votes, open votes can still close
+pub const DEFERRED_VOTES: Map<(u32, u16), Vote> = Map::new("deferred_vote_map"); // votes that would open a tx while paused by (tx_id, chain_id), replayed in pages
+pub const MAX_REPLAY: u32 = 30; // deferred votes replayed per call
+pub const SUBMITTERS: Map<&Addr, Empty> = Map::new("submitters"); // may register txs besides the admin
+
 // ibc relevant, use connection_id to differentiate chains
 pub const MY_CHANNELS: Map<String, ChannelInfo> = Map::new("my_channels");
+pub const ALLOWED_PORTS: Map<String, Empty> = Map::new("allowed_ports"); // counterparty ports of the participant contracts
 
 #[cw_serde]
 pub struct ChannelInfo {
     pub channel_id: String,
     /// whether the channel is completely set up
     pub finalized: bool,
+    /// whether the channel has been closed, a new channel may then replace it
+    #[serde(default)]
+    pub closed: bool,
+    /// retired by the admin, e.g. once its light client expired, no decisions are queued for it
+    #[serde(default)]
+    pub retired: bool,
+    /// participant chain, known once it voted over the channel
+    #[serde(default)]
+    pub chain_id: Option<u16>,
 }
 
 pub const MY_LOGS: Item<String> = Item::new("my_logs");
@@ -0,0 +1,465 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_ibc_channel_connect_confirm, mock_ibc_channel_open_init, mock_ibc_channel_open_try, mock_ibc_packet_recv, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{from_json, CosmosMsg, IbcMsg, IbcOrder, OwnedDeps, Response, SubMsg};
+use cw_storage_plus::Map;
+
+use crate::error::ContractError;
+use crate::ibc::IBC_APP_VERSION;
+use crate::msg::{AbortReason, ChainDigest, ClosedVotesResp, CommitPolicy, DecisionLogResp, ExecuteMsg, InstantiateMsg, Instructions, MigrateMsg, PauseStateResp, QueryMsg, TxState, TxStatusResp, Vote, Votes};
+use crate::state::{ChannelInfo, BUFFERED_INSTRUCTIONS, CLOSED_VOTES, DEFERRED_VOTES, MY_CHANNELS};
+
+type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+fn setup(chain_num: u16) -> Deps {
+    let mut deps = mock_dependencies();
+    crate::instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), InstantiateMsg{
+        chain_num,
+        admin: None,
+        allowed_ports: None,
+    }).unwrap();
+    deps
+}
+
+fn execute(deps: &mut Deps, sender: &str, msg: ExecuteMsg) -> Response {
+    crate::execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg).unwrap()
+}
+
+fn vote(tx_id: u32, chain_id: u16, success: bool) -> ExecuteMsg {
+    ExecuteMsg::AddVote { vote: Vote{ tx_id, chain_id, success, digest: None, abort_reason: None } }
+}
+
+fn tx_status(deps: &Deps, tx_id: u32) -> TxStatusResp {
+    from_json(crate::query(deps.as_ref(), mock_env(), QueryMsg::TxStatus { tx_id }).unwrap()).unwrap()
+}
+
+fn tx_state(deps: &Deps, tx_id: u32) -> TxState {
+    tx_status(deps, tx_id).state
+}
+
+fn deferred_count(deps: &Deps) -> usize {
+    DEFERRED_VOTES.keys(deps.as_ref().storage, None, None, cosmwasm_std::Order::Ascending).count()
+}
+
+#[test]
+fn paused_votes_are_deferred_and_replayed_in_pages() {
+    let mut deps = setup(1);
+    execute(&mut deps, "admin", ExecuteMsg::Pause {});
+    for tx_id in 1..=40 {
+        execute(&mut deps, "admin", vote(tx_id, 1, true));
+    }
+    assert_eq!(deferred_count(&deps), 40);
+    assert_eq!(tx_state(&deps, 1), TxState::Unknown);
+
+    // the deferred vote of a chain is replaced, not duplicated
+    execute(&mut deps, "admin", vote(1, 1, true));
+    assert_eq!(deferred_count(&deps), 40);
+
+    // replaying is refused while paused
+    execute(&mut deps, "anyone", ExecuteMsg::ReplayDeferred { limit: None });
+    assert_eq!(deferred_count(&deps), 40);
+
+    // resume only replays the first page
+    execute(&mut deps, "admin", ExecuteMsg::Resume {});
+    assert_eq!(deferred_count(&deps), 10);
+    assert_eq!(tx_state(&deps, 30), TxState::Closed);
+    assert_eq!(tx_state(&deps, 31), TxState::Unknown);
+    let state: PauseStateResp = from_json(crate::query(deps.as_ref(), mock_env(), QueryMsg::PauseState {}).unwrap()).unwrap();
+    assert_eq!(state.deferred_votes.first().map(|v| v.tx_id), Some(31));
+
+    execute(&mut deps, "anyone", ExecuteMsg::ReplayDeferred { limit: Some(4) });
+    assert_eq!(deferred_count(&deps), 6);
+    execute(&mut deps, "anyone", ExecuteMsg::ReplayDeferred { limit: None });
+    assert_eq!(deferred_count(&deps), 0);
+    assert_eq!(tx_state(&deps, 40), TxState::Closed);
+}
+
+#[test]
+fn closed_votes_of_0_1_0_are_converted_in_pages() {
+    let mut deps = setup(1);
+    let storage = deps.as_mut().storage;
+    cw2::set_contract_version(storage, "crates.io:coordinator1", "0.1.0").unwrap();
+    let legacy: Map<u32, bool> = Map::new("closed_votes");
+    for tx_id in 1..=150 {
+        legacy.save(storage, tx_id, &(tx_id % 2 == 0)).unwrap();
+    }
+    crate::migrate(deps.as_mut(), mock_env(), MigrateMsg{ admin: None }).unwrap();
+    assert_eq!(tx_state(&deps, 100), TxState::Closed);
+    // an unconverted tx cannot be opened again
+    assert!(crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), vote(120, 1, true)).is_err());
+
+    // a decision closed meanwhile is skipped
+    execute(&mut deps, "admin", vote(200, 1, true));
+    let resp = execute(&mut deps, "anyone", ExecuteMsg::MigrateClosedVotes { limit: None });
+    assert!(resp.attributes.iter().any(|a| a.key == "remaining" && a.value == "false"));
+    assert_eq!(tx_state(&deps, 150), TxState::Closed);
+    let status = tx_status(&deps, 200);
+    assert_eq!(status.decision, Some(true));
+    assert!(status.decision_hash.is_some());
+}
+
+fn save_channel(deps: &mut Deps, connection_id: &str, channel_id: &str, closed: bool) {
+    MY_CHANNELS.save(deps.as_mut().storage, connection_id.to_string(), &ChannelInfo{
+        channel_id: channel_id.to_string(),
+        finalized: true,
+        closed,
+        retired: false,
+        chain_id: None,
+    }).unwrap();
+}
+
+fn sent_channels(resp: &Response) -> Vec<String> {
+    resp.messages.iter().filter_map(|m| match &m.msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket{ channel_id, .. }) => Some(channel_id.clone()),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn each_connection_keeps_its_own_buffer() {
+    let mut deps = setup(1);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    save_channel(&mut deps, "connection-1", "channel-1", true);
+
+    let resp = execute(&mut deps, "admin", vote(1, 1, true));
+    assert_eq!(sent_channels(&resp), vec!["channel-0"]);
+    assert!(BUFFERED_INSTRUCTIONS.has(deps.as_ref().storage, "connection-1".to_string()));
+
+    // the closed connection gets its decisions once a new channel is open
+    save_channel(&mut deps, "connection-1", "channel-2", false);
+    let resp = execute(&mut deps, "anyone", ExecuteMsg::Flush {});
+    assert_eq!(sent_channels(&resp), vec!["channel-2"]);
+    let resp = execute(&mut deps, "anyone", ExecuteMsg::Flush {});
+    assert!(sent_channels(&resp).is_empty());
+}
+
+fn sent_instructions(msgs: &[SubMsg]) -> Vec<(String, Instructions)> {
+    msgs.iter().filter_map(|m| match &m.msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket{ channel_id, data, .. }) => Some((channel_id.clone(), from_json(data).unwrap())),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn vote_for_a_closed_tx_gets_the_decision_again() {
+    let mut deps = setup(1);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    save_channel(&mut deps, "connection-1", "channel-1", false);
+    execute(&mut deps, "admin", vote(1, 1, true));
+
+    // resent after a rebind, only the connection it arrived on is answered
+    let packet = mock_ibc_packet_recv("channel-1", &Vote{ tx_id: 1, chain_id: 1, success: true, digest: None, abort_reason: None }).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    let sent = sent_instructions(&resp.messages);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].0, "channel-1");
+    assert_eq!((sent[0].1.items[0].tx_id, sent[0].1.items[0].commitment), (1, true));
+}
+
+#[test]
+fn retired_connection_gets_no_decisions() {
+    let mut deps = setup(1);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    save_channel(&mut deps, "connection-1", "channel-1", true);
+    execute(&mut deps, "admin", vote(1, 1, true));
+    assert!(BUFFERED_INSTRUCTIONS.has(deps.as_ref().storage, "connection-1".to_string()));
+
+    let retire = ExecuteMsg::RetireConnection { connection_id: "connection-1".to_string() };
+    assert!(crate::execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), retire.clone()).is_err());
+    execute(&mut deps, "admin", retire);
+    assert!(!BUFFERED_INSTRUCTIONS.has(deps.as_ref().storage, "connection-1".to_string()));
+
+    execute(&mut deps, "admin", vote(2, 1, true));
+    assert!(!BUFFERED_INSTRUCTIONS.has(deps.as_ref().storage, "connection-1".to_string()));
+}
+
+#[test]
+fn force_commit_needs_every_reachable_participant() {
+    let mut deps = setup(2);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    save_channel(&mut deps, "connection-1", "channel-1", false);
+    let force = |tx_id: u32, commit: bool| ExecuteMsg::ForceDecide { tx_id, commit, reason: "lost chain".to_string() };
+
+    // only open votes can be forced
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), force(1, false)),
+        Err(ContractError::NotOpen { tx_id: 1 })
+    ));
+
+    let packet = mock_ibc_packet_recv("channel-0", &Vote{ tx_id: 1, chain_id: 1, success: true, digest: None, abort_reason: None }).unwrap();
+    crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    let packet = mock_ibc_packet_recv("channel-1", &Vote{ tx_id: 2, chain_id: 2, success: true, digest: None, abort_reason: None }).unwrap();
+    crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+
+    // chain 2 still has an open channel
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), force(1, true)),
+        Err(ContractError::ReachableWithoutSuccess { tx_id: 1, chain_id: 2 })
+    ));
+    execute(&mut deps, "admin", ExecuteMsg::RetireConnection { connection_id: "connection-1".to_string() });
+    let resp = execute(&mut deps, "admin", force(1, true));
+    assert_eq!(tx_state(&deps, 1), TxState::Closed);
+
+    // the lost chain aborts its part whenever it gets the decision
+    let status = tx_status(&deps, 1);
+    assert_eq!((status.decision, status.aborted_chains), (Some(true), vec![2]));
+    let sent = sent_instructions(&resp.messages);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].1.items[0].aborted_chains, vec![2]);
+}
+
+#[test]
+fn only_the_admin_and_allowed_submitters_register() {
+    let mut deps = setup(1);
+    let register = |tx_id: u32| ExecuteMsg::RegisterTx {
+        tx_id,
+        digests: vec![ChainDigest{ chain_id: 1, digest: "aa".to_string() }],
+        policy: None,
+    };
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("griefer", &[]), register(1)),
+        Err(ContractError::Unauthorized { .. })
+    ));
+    execute(&mut deps, "admin", ExecuteMsg::AllowSubmitter { address: "app".to_string() });
+    execute(&mut deps, "app", register(1));
+    execute(&mut deps, "admin", register(2));
+
+    execute(&mut deps, "admin", ExecuteMsg::DisallowSubmitter { address: "app".to_string() });
+    assert!(crate::execute(deps.as_mut(), mock_env(), mock_info("app", &[]), register(3)).is_err());
+}
+
+#[test]
+fn only_a_still_allowed_registrant_requests_an_abort() {
+    let mut deps = setup(2);
+    execute(&mut deps, "admin", ExecuteMsg::AllowSubmitter { address: "app".to_string() });
+    for tx_id in 1..=2 {
+        execute(&mut deps, "app", ExecuteMsg::RegisterTx { tx_id, digests: Vec::new(), policy: None });
+        execute(&mut deps, "admin", vote(tx_id, 1, true));
+    }
+    let abort = |tx_id: u32| ExecuteMsg::RequestAbort { tx_id };
+    assert!(crate::execute(deps.as_mut(), mock_env(), mock_info("other", &[]), abort(1)).is_err());
+    execute(&mut deps, "app", abort(1));
+    assert_eq!(tx_state(&deps, 1), TxState::Closed);
+
+    execute(&mut deps, "admin", ExecuteMsg::DisallowSubmitter { address: "app".to_string() });
+    assert!(crate::execute(deps.as_mut(), mock_env(), mock_info("app", &[]), abort(2)).is_err());
+    execute(&mut deps, "admin", abort(2));
+    assert_eq!(tx_state(&deps, 2), TxState::Closed);
+}
+
+#[test]
+fn policies_are_admin_only_and_hashed_with_aborted_chains() {
+    let mut deps = setup(2);
+    execute(&mut deps, "admin", ExecuteMsg::AllowSubmitter { address: "app".to_string() });
+    let register = |tx_id: u32| ExecuteMsg::RegisterTx { tx_id, digests: Vec::new(), policy: Some(CommitPolicy::Quorum { k: 1 }) };
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("app", &[]), register(1)),
+        Err(ContractError::Unauthorized { .. })
+    ));
+    execute(&mut deps, "admin", register(1));
+
+    // chain 2 fails, the quorum commits and chain 2 aborts its part
+    execute(&mut deps, "admin", vote(1, 2, false));
+    execute(&mut deps, "admin", vote(1, 1, true));
+    let log: DecisionLogResp = from_json(crate::query(deps.as_ref(), mock_env(), QueryMsg::DecisionLog { start_after: None, limit: None }).unwrap()).unwrap();
+    let (_, entry) = &log.entries[0];
+    assert_eq!((entry.commitment, entry.aborted_chains.clone()), (true, vec![2]));
+    assert_eq!(entry.hash, crate::utils::decision_hash("", 1, true, &[2], entry.height).unwrap());
+    assert_ne!(entry.hash, crate::utils::decision_hash("", 1, true, &[], entry.height).unwrap());
+}
+
+#[test]
+fn only_the_admin_adds_votes() {
+    let mut deps = setup(2);
+    for success in [true, false] {
+        assert!(matches!(
+            crate::execute(deps.as_mut(), mock_env(), mock_info("griefer", &[]), vote(1, 1, success)),
+            Err(ContractError::Unauthorized { .. })
+        ));
+    }
+    assert_eq!(tx_state(&deps, 1), TxState::Unknown);
+    execute(&mut deps, "admin", vote(1, 1, true));
+    assert_eq!(tx_state(&deps, 1), TxState::Open);
+}
+
+#[test]
+fn votes_of_unknown_chains_are_rejected() {
+    let mut deps = setup(2);
+    for chain_id in [0, 3, 99] {
+        assert!(matches!(
+            crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), vote(1, chain_id, true)),
+            Err(ContractError::UnknownChain { .. })
+        ));
+    }
+    // chain 2 is still missing, so the tx stays open
+    execute(&mut deps, "admin", vote(1, 1, true));
+    let status = tx_status(&deps, 1);
+    assert_eq!((status.state, status.missing_chains), (TxState::Open, vec![2]));
+}
+
+#[test]
+fn decisions_from_before_the_log_carry_no_hash() {
+    let mut deps = setup(1);
+    let storage = deps.as_mut().storage;
+    cw2::set_contract_version(storage, "crates.io:coordinator1", "0.1.0").unwrap();
+    let legacy: Map<u32, bool> = Map::new("closed_votes");
+    legacy.save(storage, 1, &true).unwrap();
+    crate::migrate(deps.as_mut(), mock_env(), MigrateMsg{ admin: None }).unwrap();
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+
+    let packet = mock_ibc_packet_recv("channel-0", &Vote{ tx_id: 1, chain_id: 1, success: true, digest: None, abort_reason: None }).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    let sent = sent_instructions(&resp.messages);
+    assert_eq!(sent[0].1.items[0].decision_hash, None);
+
+    execute(&mut deps, "admin", vote(2, 1, true));
+    assert!(tx_status(&deps, 2).decision_hash.is_some());
+}
+
+fn success(tx_id: u32, chain_id: u16) -> Vote {
+    Vote{ tx_id, chain_id, success: true, digest: None, abort_reason: None }
+}
+
+#[test]
+fn batched_votes_close_each_tx() {
+    let mut deps = setup(2);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    save_channel(&mut deps, "connection-1", "channel-1", false);
+    let receive = |deps: &mut Deps, channel_id: &str, votes: Vec<Vote>| {
+        let packet = mock_ibc_packet_recv(channel_id, &Votes{ items: votes }).unwrap();
+        crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap()
+    };
+    receive(&mut deps, "channel-0", vec![success(1, 1), success(2, 1), success(3, 1)]);
+    for tx_id in 1..=3 {
+        assert_eq!(tx_state(&deps, tx_id), TxState::Open);
+    }
+
+    // a vote that does not apply is skipped, the rest of the batch still counts
+    let failure = Vote{ tx_id: 2, chain_id: 2, success: false, digest: None, abort_reason: None };
+    let resp = receive(&mut deps, "channel-1", vec![success(1, 2), success(1, 2), failure, success(3, 2)]);
+    assert_eq!(tx_status(&deps, 1).decision, Some(true));
+    assert_eq!(tx_status(&deps, 2).decision, Some(false));
+    assert_eq!(tx_status(&deps, 3).decision, Some(true));
+
+    // the decisions go out as one packet per connection
+    let sent = sent_instructions(&resp.messages);
+    assert_eq!(sent.len(), 2);
+    for (_, instructions) in sent {
+        let decided = instructions.items.iter().map(|i| (i.tx_id, i.commitment)).collect::<Vec<_>>();
+        assert_eq!(decided, vec![(1, true), (2, false), (3, true)]);
+    }
+}
+
+#[test]
+fn digest_mismatch_aborts_with_a_safety_event() {
+    let mut deps = setup(2);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    execute(&mut deps, "admin", ExecuteMsg::RegisterTx {
+        tx_id: 1,
+        digests: vec![ChainDigest{ chain_id: 1, digest: "aa".to_string() }],
+        policy: None,
+    });
+    // a success vote for other content still aborts
+    let vote = Vote{ tx_id: 1, chain_id: 1, success: true, digest: Some("bb".to_string()), abort_reason: None };
+    let packet = mock_ibc_packet_recv("channel-0", &vote).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+
+    let event = resp.events.iter().find(|e| e.ty == "safety_violation").unwrap();
+    let attr = |key: &str| event.attributes.iter().find(|a| a.key == key).map(|a| a.value.clone());
+    assert_eq!((attr("expected_digest"), attr("digest")), (Some("aa".to_string()), Some("bb".to_string())));
+    let status = tx_status(&deps, 1);
+    assert_eq!((status.decision, status.abort_reason), (Some(false), Some(AbortReason::DigestMismatch)));
+    assert_eq!(sent_instructions(&resp.messages)[0].1.items[0].abort_reason, Some(AbortReason::DigestMismatch));
+
+    // the registered digest counts as usual
+    execute(&mut deps, "admin", ExecuteMsg::RegisterTx {
+        tx_id: 2,
+        digests: vec![ChainDigest{ chain_id: 1, digest: "aa".to_string() }],
+        policy: None,
+    });
+    let vote = Vote{ tx_id: 2, chain_id: 1, success: true, digest: Some("aa".to_string()), abort_reason: None };
+    let packet = mock_ibc_packet_recv("channel-0", &vote).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    assert!(resp.events.iter().all(|e| e.ty != "safety_violation"));
+    assert_eq!(tx_state(&deps, 2), TxState::Open);
+}
+
+#[test]
+fn failure_reason_reaches_the_instruction() {
+    let mut deps = setup(2);
+    save_channel(&mut deps, "connection-0", "channel-0", false);
+    let failure = Vote{ tx_id: 1, chain_id: 2, success: false, digest: None, abort_reason: Some(AbortReason::InsufficientBalance) };
+    let votes = Votes{ items: vec![success(1, 1), failure] };
+    let packet = mock_ibc_packet_recv("channel-0", &votes).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+
+    let closed = CLOSED_VOTES.load(deps.as_ref().storage, 1).unwrap();
+    assert_eq!((closed.commitment, closed.abort_reason), (false, Some(AbortReason::InsufficientBalance)));
+    assert_eq!(tx_status(&deps, 1).abort_reason, Some(AbortReason::InsufficientBalance));
+    let sent = sent_instructions(&resp.messages);
+    assert_eq!(sent[0].1.items[0].abort_reason, Some(AbortReason::InsufficientBalance));
+
+    // commits carry no reason
+    let resp = execute(&mut deps, "admin", vote(2, 1, true));
+    assert!(resp.messages.is_empty());
+    let packet = mock_ibc_packet_recv("channel-0", &success(2, 2)).unwrap();
+    let resp = crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    assert_eq!(CLOSED_VOTES.load(deps.as_ref().storage, 2).unwrap().abort_reason, None);
+    assert_eq!(sent_instructions(&resp.messages)[0].1.items[0].abort_reason, None);
+}
+
+#[test]
+fn tx_status_and_closed_votes_report_the_votes() {
+    let mut deps = setup(3);
+    let unknown = tx_status(&deps, 1);
+    assert_eq!((unknown.state, unknown.missing_chains), (TxState::Unknown, vec![1, 2, 3]));
+    execute(&mut deps, "admin", vote(1, 1, true));
+    execute(&mut deps, "admin", vote(1, 3, true));
+    let open = tx_status(&deps, 1);
+    assert_eq!((open.state, open.voted_chains, open.missing_chains, open.decision), (TxState::Open, vec![1, 3], vec![2], None));
+
+    execute(&mut deps, "admin", vote(1, 2, true));
+    execute(&mut deps, "admin", vote(2, 1, false));
+    for chain_id in 1..=3 {
+        execute(&mut deps, "admin", vote(3, chain_id, true));
+    }
+    execute(&mut deps, "admin", vote(4, 2, false));
+    let closed = tx_status(&deps, 1);
+    assert_eq!((closed.state, closed.missing_chains, closed.decision), (TxState::Closed, vec![], Some(true)));
+
+    let closed_votes = |deps: &Deps, start_after, limit, committed| {
+        let resp: ClosedVotesResp = from_json(crate::query(deps.as_ref(), mock_env(), QueryMsg::ClosedVotes { start_after, limit, committed }).unwrap()).unwrap();
+        resp.votes
+    };
+    assert_eq!(closed_votes(&deps, None, Some(2), None), vec![(1, true), (2, false)]);
+    assert_eq!(closed_votes(&deps, Some(2), None, None), vec![(3, true), (4, false)]);
+    assert_eq!(closed_votes(&deps, None, None, Some(false)), vec![(2, false), (4, false)]);
+    assert_eq!(closed_votes(&deps, Some(1), Some(1), Some(true)), vec![(3, true)]);
+}
+
+#[test]
+fn handshake_checks_order_version_and_allowed_ports() {
+    let mut deps = mock_dependencies();
+    crate::instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), InstantiateMsg{
+        chain_num: 1,
+        admin: None,
+        allowed_ports: Some(vec!["their_port".to_string()]),
+    }).unwrap();
+    let open = |deps: &mut Deps, msg| crate::ibc::ibc_channel_open(deps.as_mut(), mock_env(), msg);
+
+    let err = open(&mut deps, mock_ibc_channel_open_try("channel-0", IbcOrder::Ordered, IBC_APP_VERSION)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcOrder {});
+    let err = open(&mut deps, mock_ibc_channel_open_init("channel-0", IbcOrder::Unordered, "myfc-0")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcVersion { version: "myfc-0".to_string(), expected: IBC_APP_VERSION.to_string() });
+    assert!(!MY_CHANNELS.has(deps.as_ref().storage, "connection-2".to_string()));
+
+    let resp = open(&mut deps, mock_ibc_channel_open_try("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap();
+    assert_eq!(resp.unwrap().version, IBC_APP_VERSION);
+    let err = crate::ibc::ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_confirm("channel-0", IbcOrder::Ordered, IBC_APP_VERSION)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcOrder {});
+    crate::ibc::ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_confirm("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap();
+    assert!(MY_CHANNELS.load(deps.as_ref().storage, "connection-2".to_string()).unwrap().finalized);
+
+    // ports outside the allowed list are refused
+    let mut deps = setup(1);
+    let err = open(&mut deps, mock_ibc_channel_open_init("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidCounterpartyPort { port_id: "their_port".to_string() });
+}
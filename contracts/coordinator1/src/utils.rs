@@ -0,0 +1,62 @@
+use cosmwasm_std::{Addr, Deps, Event, StdError, StdResult};
+use semver::Version;
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::state::{ADMIN, PAUSED, SUBMITTERS};
+
+pub fn ensure_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    match ADMIN.may_load(deps.storage)? {
+        Some(admin) if admin == *sender => Ok(()),
+        _ => Err(ContractError::Unauthorized { sender: sender.clone() }),
+    }
+}
+
+// the admin or an allowed submitter
+pub fn ensure_submitter(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if SUBMITTERS.has(deps.storage, sender) {
+        return Ok(())
+    }
+    ensure_admin(deps, sender)
+}
+
+pub fn is_paused(deps: Deps) -> StdResult<bool> {
+    Ok(PAUSED.may_load(deps.storage)?.unwrap_or(false))
+}
+
+// rejected calls succeed without opening anything so that the event is kept
+pub fn paused_rejection_event(action: &str) -> Event {
+    Event::new("paused_rejection")
+    .add_attribute("action", action)
+}
+
+// link of the decision log, hex sha256 of the json of [prev, tx_id, commitment, aborted_chains, height]
+pub fn decision_hash(prev: &str, tx_id: u32, commitment: bool, aborted_chains: &[u16], height: u64) -> StdResult<String> {
+    let data = cosmwasm_std::to_json_vec(&(prev, tx_id, commitment, aborted_chains, height))?;
+    Ok(hex::encode(Sha256::digest(data)))
+}
+
+// instances deployed before versioning was introduced have no cw2 record
+pub const LEGACY_VERSION: &str = "0.1.0";
+
+// check the stored contract before migrating, returns the previous version
+pub fn check_migration(deps: Deps, name: &str, version: &str) -> Result<Version, ContractError> {
+    let previous_version = match cw2::CONTRACT.may_load(deps.storage)? {
+        Some(previous) => {
+            if previous.contract != name {
+                return Err(ContractError::InvalidMigrationContract { previous_contract: previous.contract })
+            }
+            previous.version
+        },
+        None => LEGACY_VERSION.to_string(),
+    };
+    let previous = parse_version(&previous_version)?;
+    if previous > parse_version(version)? {
+        return Err(ContractError::InvalidMigrationVersion { previous_version, new_version: version.to_string() })
+    }
+    Ok(previous)
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version).map_err(|e| StdError::generic_err(e.to_string()))
+}
@@ -5,14 +5,16 @@ fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
-        query: QueryMsg
+        query: QueryMsg,
+        migrate: MigrateMsg
     }
 
     // generate a valid json
     let msg = ExecuteMsg::ExecuteTx {
         fcross_tx: FcrossTx {
             tx_id: 1,
-            operation: Operation::CreditBalance { amount: 100 },
+            operation: Operation::CreditBalance { denom: "stake".to_string(), amount: 100u128.into() },
+            callback: None,
         },
     };
     let json = serde_json::to_string(&msg).unwrap();
@@ -20,7 +22,7 @@ fn main() {
     let msg2 = QueryMsg::MyLogs {  };
     let json2 = serde_json::to_string(&msg2).unwrap();
     println!("{}", json2);
-    let msg3 = QueryMsg::Multifuture { tx_id: 1 };
+    let msg3 = QueryMsg::Multifuture { tx_id: 1, start_after: None, limit: None };
     let json3 = serde_json::to_string(&msg3).unwrap();
     println!("{}", json3);
 }
@@ -1,96 +1,413 @@
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, IbcMsg
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult, IbcMsg, SubMsgResult, Uint128
 };
+use cw2::set_contract_version;
 use crate::error::ContractError;
-use crate::msg::{InstantiateMsg, QueryMsg, ExecuteMsg};
-use crate::state::{CHAIN_ID, EXPECTED_TX_ID, MF_MAP, MF_VOTE_MAP, PENDING_TX_LIST, MY_LOGS};
+use crate::msg::{InstantiateMsg, QueryMsg, ExecuteMsg, ExecuteBatchResp, Instruction, MigrateMsg, TxDigestResp};
+use crate::state::{Balances, ADMIN, CHAIN_ID, COORDINATOR_PORT, ESCROW_DENOM, EXPECTED_TX_ID, MF_MAP, MF_VOTE_MAP, PAUSED, PENDING_TX_LIST, MY_LOGS, TOKEN_INFO};
+use crate::utils;
+
+const CONTRACT_NAME: &str = "crates.io:mf1";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     MY_LOGS.save(deps.storage, &"start:".to_string())?;
 
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
+    ADMIN.save(deps.storage, &admin)?;
+    PAUSED.save(deps.storage, &false)?;
+    if let Some(port_id) = msg.coordinator_port {
+        COORDINATOR_PORT.save(deps.storage, &port_id)?;
+    }
+    if let Some(denom) = msg.escrow_denom {
+        // the original balance of the escrow denom must be attached, so every ledger unit is held
+        let amount_of = |coins: &[cosmwasm_std::Coin]| coins.iter().filter(|c| c.denom == denom).map(|c| c.amount).sum::<Uint128>();
+        let expected = amount_of(&msg.original_balances);
+        let received = amount_of(&info.funds);
+        if expected != received {
+            return Err(ContractError::EscrowFundsMismatch { expected, received })
+        }
+        ESCROW_DENOM.save(deps.storage, &denom)?;
+    }
+    TOKEN_INFO.save(deps.storage, &msg.token.unwrap_or_else(|| utils::default_token_meta(msg.chain_id)))?;
+
     CHAIN_ID.save(deps.storage, &msg.chain_id)?;
     PENDING_TX_LIST.save(deps.storage, &Vec::new())?;
     EXPECTED_TX_ID.save(deps.storage, &1)?;
-    MF_MAP.save(deps.storage, 0, &vec![Some(msg.original_value)])?;
+    let original = msg.original_balances
+    .iter()
+    .filter(|c| !c.amount.is_zero())
+    .map(|c| (c.denom.clone(), c.amount))
+    .collect::<Balances>();
+    MF_MAP.save(deps.storage, 0, &vec![Some(original)])?;
     MF_VOTE_MAP.save(deps.storage, 0, &true)?;
     Ok(Response::new()
     .add_attribute("method", "instantiate")
+    .add_attribute("admin", admin)
     .add_attribute("initiated_chain", msg.chain_id.to_string())
-    .add_attribute("original_value", msg.original_value.to_string()))
+    .add_attribute("original_balances", msg.original_balances.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(",")))
+}
+
+/* MIGRATION */
+pub fn migrate(
+    mut deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let previous_version = utils::check_migration(deps.as_ref(), CONTRACT_NAME, CONTRACT_VERSION)?;
+    migration::run(&mut deps, &previous_version, msg.legacy_denom)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let mut resp = Response::new()
+    .add_attribute("method", "migrate")
+    .add_attribute("previous_version", previous_version.to_string())
+    .add_attribute("new_version", CONTRACT_VERSION);
+    if let Some(admin) = msg.admin {
+        let admin = deps.api.addr_validate(&admin)?;
+        ADMIN.save(deps.storage, &admin)?;
+        resp = resp.add_attribute("admin", admin);
+    }
+    Ok(resp)
+}
+
+mod migration {
+    use cw_storage_plus::Map;
+    use semver::Version;
+
+    use super::*;
+
+    // storage layout before 0.3.0, older layouts only differ in records that are created on demand
+    const MF_MAP_V0_2: Map<u32, Vec<Option<i64>>> = Map::new("mf_maps");
+
+    // bring the storage of an older version up to date, one step per layout change
+    pub fn run(deps: &mut DepsMut, previous: &Version, legacy_denom: Option<String>) -> Result<(), ContractError> {
+        if *previous < Version::new(0, 3, 0) {
+            let denom = match legacy_denom.or(ESCROW_DENOM.may_load(deps.storage)?) {
+                Some(denom) => denom,
+                None => return Err(ContractError::MissingLegacyDenom {}),
+            };
+            mf_map_v0_3(deps, &denom)?;
+        }
+        Ok(())
+    }
+
+    // i64 values became amounts of a single denom, a negative value cannot be held and fails its branch.
+    // Only the last committed mf and the later ones are read again, not every mf since instantiation
+    fn mf_map_v0_3(deps: &mut DepsMut, denom: &str) -> Result<(), ContractError> {
+        let committed = utils::last_committed_tx_id(deps.as_ref())?;
+        let expected = EXPECTED_TX_ID.load(deps.storage)?;
+        if let Some(&Some(value)) = MF_MAP_V0_2.load(deps.storage, committed)?.first() {
+            if value < 0 {
+                return Err(ContractError::NegativeCommittedValue { tx_id: committed, value })
+            }
+        }
+        for tx_id in committed..expected {
+            let mf = MF_MAP_V0_2
+            .load(deps.storage, tx_id)?
+            .into_iter()
+            .map(|v| {
+                let v = u128::try_from(v?).ok()?;
+                utils::update_denom(&Balances::new(), denom, |_| Some(v.into()))
+            })
+            .collect::<Vec<Option<Balances>>>();
+            MF_MAP.save(deps.storage, tx_id, &mf)?;
+        }
+        Ok(())
+    }
+}
+
+/* REPLY */
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
+    // only failed callbacks reply, keep the finalization and record the failure
+    if let SubMsgResult::Err(e) = msg.result {
+        let mut logs = MY_LOGS.load(deps.storage)?;
+        logs.push_str(format!("\ncallback failed: {}", e).as_str());
+        MY_LOGS.save(deps.storage, &logs)?;
+        return Ok(Response::new().add_attribute("callback_failed", e))
+    }
+    Ok(Response::new())
 }
 
 /* QUERY */
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     use QueryMsg::*;
 
     match msg {
-        Multifuture{ tx_id } => to_json_binary(&query::multifuture(deps, tx_id)?),
+        Multifuture{ tx_id, start_after, limit } => to_json_binary(&query::multifuture(deps, tx_id, start_after, limit)?),
         MyLogs{} => to_json_binary(&query::my_logs(deps)?),
+        PipelineState{} => to_json_binary(&query::pipeline_state(deps)?),
+        TxStatus{ tx_id } => to_json_binary(&query::tx_status(deps, tx_id)?),
+        CommittedValue{} => to_json_binary(&query::committed_value(deps)?),
+        ValueRange{ denom } => to_json_binary(&query::value_range(deps, denom)?),
+        PendingOutcomes{} => to_json_binary(&query::pending_outcomes(deps)?),
+        Simulate{ fcross_tx } => to_json_binary(&query::simulate(deps, &fcross_tx)?),
+        PauseState{} => to_json_binary(&query::pause_state(deps)?),
+        Escrow{ address } => to_json_binary(&query::escrow(deps, env, address)?),
+        TxDigest{ fcross_tx } => to_json_binary(&TxDigestResp{ digest: utils::tx_digest(&fcross_tx)? }),
+        Balance{ address } => to_json_binary(&query::balance(deps, address)?),
+        TokenInfo{} => to_json_binary(&query::token_info(deps)?),
     }
 }
 
 mod query {
-    use crate::msg::{MultifutureResp, MyLogsResp};
+    use crate::msg::{BalanceResponse, CommittedValueResp, EscrowResp, FcrossTx, FutureEntry, MultifutureResp, MyLogsResp, PauseStateResp, PendingOutcomesResp, PipelineStateResp, SimulateResp, TokenInfoResponse, TxStatusResp, ValueRangeResp};
+    use cosmwasm_std::{StdError, Uint128};
+    use super::exec::{apply_operation, check_execution_stautus};
+    use crate::state::{MAX_PENDING_LEN, MF_ABORT_REASON_MAP, MF_DECISION_HASH_MAP, MF_DECISION_MAP, MF_FAILURE_REASON_MAP, MF_SENT_VOTE_MAP, MY_CHANNEL, REBIND_APPROVED};
 
     use super::*;
 
-    pub fn multifuture(deps: Deps, tx_id: u32) -> StdResult<MultifutureResp> {
-        let mf = MF_MAP
-        .load(deps.storage, tx_id)?
+    const DEFAULT_LIMIT: u32 = 256;
+    const MAX_LIMIT: u32 = 1024;
+
+    pub fn multifuture(deps: Deps, tx_id: u32, start_after: Option<u32>, limit: Option<u32>) -> StdResult<MultifutureResp> {
+        let mf = MF_MAP.load(deps.storage, tx_id)?;
+        // the mf of tx_id branches on every still pending tx up to tx_id
+        let pending = PENDING_TX_LIST
+        .load(deps.storage)?
         .into_iter()
-        .map(|i|{
-            match i {
-                None=>"None".to_string(),
-                Some(v)=>v.to_string(),
-            }
+        .filter(|&i| i <= tx_id)
+        .collect::<Vec<u32>>();
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|i| i as usize + 1).unwrap_or(0);
+        let futures = mf
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(limit)
+        .map(|(index, value)| FutureEntry{
+            index: index as u32,
+            label: utils::future_index_to_string(index as u16, pending.len()),
+            value: value.clone(),
+        })
+        .collect::<Vec<FutureEntry>>();
+        Ok(MultifutureResp{
+            tx_id,
+            pending_tx_list: pending,
+            total: mf.len() as u32,
+            futures,
         })
-        .collect::<Vec<String>>();
-        Ok(MultifutureResp{futures: mf})
     }
 
     pub fn my_logs(deps: Deps) -> StdResult<MyLogsResp> {
         let logs = MY_LOGS.load(deps.storage)?;
         Ok(MyLogsResp{logs})
     }
+
+    pub fn pipeline_state(deps: Deps) -> StdResult<PipelineStateResp> {
+        let pending = PENDING_TX_LIST.load(deps.storage)?;
+        let pending_votes = pending
+        .iter()
+        .map(|&i| Ok((i, MF_VOTE_MAP.load(deps.storage, i)?)))
+        .collect::<StdResult<Vec<(u32, bool)>>>()?;
+        Ok(PipelineStateResp{
+            chain_id: CHAIN_ID.load(deps.storage)?,
+            expected_tx_id: EXPECTED_TX_ID.load(deps.storage)?,
+            pending_tx_list: pending,
+            pending_votes,
+            pending_len: utils::calculate_pending_list_len(deps)?,
+            max_pending_len: MAX_PENDING_LEN,
+            channel: MY_CHANNEL.may_load(deps.storage)?,
+            coordinator_port: COORDINATOR_PORT.may_load(deps.storage)?,
+            rebind_approved: REBIND_APPROVED.may_load(deps.storage)?.unwrap_or(false),
+        })
+    }
+
+    pub fn tx_status(deps: Deps, tx_id: u32) -> StdResult<TxStatusResp> {
+        let expected = EXPECTED_TX_ID.load(deps.storage)?;
+        let executed = tx_id > 0 && tx_id < expected;
+        let pending = PENDING_TX_LIST.load(deps.storage)?;
+        Ok(TxStatusResp{
+            tx_id,
+            executed,
+            voted: executed && MF_VOTE_MAP.may_load(deps.storage, tx_id)?.unwrap_or(false),
+            finalized: executed && !pending.contains(&tx_id),
+            vote: MF_SENT_VOTE_MAP.may_load(deps.storage, tx_id)?,
+            failure_reason: MF_FAILURE_REASON_MAP.may_load(deps.storage, tx_id)?,
+            decision: MF_DECISION_MAP.may_load(deps.storage, tx_id)?,
+            decision_hash: MF_DECISION_HASH_MAP.may_load(deps.storage, tx_id)?,
+            abort_reason: MF_ABORT_REASON_MAP.may_load(deps.storage, tx_id)?,
+        })
+    }
+
+    pub fn committed_value(deps: Deps) -> StdResult<CommittedValueResp> {
+        let tx_id = utils::last_committed_tx_id(deps)?;
+        // no pending tx branches this mf, so it holds a single future
+        let value = MF_MAP.load(deps.storage, tx_id)?.into_iter().next().flatten();
+        Ok(CommittedValueResp{ tx_id, value })
+    }
+
+    pub fn value_range(deps: Deps, denom: String) -> StdResult<ValueRangeResp> {
+        let tx_id = EXPECTED_TX_ID.load(deps.storage)?-1;
+        let mf = MF_MAP.load(deps.storage, tx_id)?;
+        let amounts = || mf.iter().flatten().map(|b| utils::amount_of(b, &denom));
+        Ok(ValueRangeResp{
+            tx_id,
+            min: amounts().min(),
+            max: amounts().max(),
+            denom,
+        })
+    }
+
+    pub fn pending_outcomes(deps: Deps) -> StdResult<PendingOutcomesResp> {
+        let tx_id = EXPECTED_TX_ID.load(deps.storage)?-1;
+        let mf = MF_MAP.load(deps.storage, tx_id)?;
+        // index 0 aborts every pending tx, the last index commits every pending tx
+        Ok(PendingOutcomesResp{
+            tx_id,
+            pending_tx_list: PENDING_TX_LIST.load(deps.storage)?,
+            all_commit: mf.last().cloned().flatten(),
+            all_abort: mf.first().cloned().flatten(),
+        })
+    }
+
+    pub fn simulate(deps: Deps, tx: &FcrossTx) -> StdResult<SimulateResp> {
+        let expected = EXPECTED_TX_ID.load(deps.storage)?;
+        super::exec::validate_operation(deps, &tx.operation).map_err(|e| StdError::generic_err(e.to_string()))?;
+        let futures = apply_operation(deps, &MF_MAP.load(deps.storage, expected-1)?, &tx.operation)?;
+        Ok(SimulateResp{
+            expected_tx_id: expected,
+            tx_id_matches: tx.tx_id == expected,
+            status: check_execution_stautus(&futures),
+            futures,
+            window_overflow: utils::calculate_pending_list_len(deps)? > MAX_PENDING_LEN,
+        })
+    }
+
+    pub fn pause_state(deps: Deps) -> StdResult<PauseStateResp> {
+        Ok(PauseStateResp{
+            paused: utils::is_paused(deps)?,
+            admin: ADMIN.may_load(deps.storage)?,
+        })
+    }
+
+    pub fn escrow(deps: Deps, env: Env, address: Option<String>) -> StdResult<EscrowResp> {
+        let head = MF_MAP.load(deps.storage, EXPECTED_TX_ID.load(deps.storage)?-1)?;
+        let denom = match ESCROW_DENOM.may_load(deps.storage)? {
+            Some(denom) => denom,
+            None => return Ok(EscrowResp{ denom: None, held: Uint128::zero(), withdrawable: None }),
+        };
+        let held = deps.querier.query_balance(&env.contract.address, &denom)?.amount;
+        let withdrawable = match address {
+            Some(address) => {
+                let account = utils::account_key(&deps.api.addr_validate(&address)?, &denom);
+                utils::certain_value(&head, &account).map(|v| v.min(held))
+            },
+            None => None,
+        };
+        Ok(EscrowResp{
+            withdrawable,
+            held,
+            denom: Some(denom),
+        })
+    }
+
+    pub fn balance(deps: Deps, address: String) -> StdResult<BalanceResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let balance = match ESCROW_DENOM.may_load(deps.storage)? {
+            Some(denom) => {
+                let head = MF_MAP.load(deps.storage, EXPECTED_TX_ID.load(deps.storage)?-1)?;
+                utils::certain_value(&head, &utils::account_key(&address, &denom)).unwrap_or_default()
+            },
+            None => Uint128::zero(),
+        };
+        Ok(BalanceResponse{ balance })
+    }
+
+    pub fn token_info(deps: Deps) -> StdResult<TokenInfoResponse> {
+        // instances from before 0.2.0 have no stored metadata
+        let meta = match TOKEN_INFO.may_load(deps.storage)? {
+            Some(meta) => meta,
+            None => utils::default_token_meta(CHAIN_ID.load(deps.storage)?),
+        };
+        let total_supply = match ESCROW_DENOM.may_load(deps.storage)? {
+            Some(denom) => {
+                let head = MF_MAP.load(deps.storage, EXPECTED_TX_ID.load(deps.storage)?-1)?;
+                utils::certain_supply(&head, &denom).unwrap_or_default()
+            },
+            None => Uint128::zero(),
+        };
+        Ok(TokenInfoResponse{
+            name: meta.name,
+            symbol: meta.symbol,
+            decimals: meta.decimals,
+            total_supply,
+        })
+    }
 }
 
 /* EXECUTION */
 pub fn execute(
     mut deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     use ExecuteMsg::*;
 
     match msg {
-        ExecuteTx{ fcross_tx } => exec::execute_tx(deps, &env, &fcross_tx),
+        ExecuteTx{ fcross_tx } => {
+            if utils::is_paused(deps.as_ref())? {
+                return Ok(Response::new().add_event(
+                    utils::paused_rejection_event("execute_tx")
+                    .add_attribute("tx_id", fcross_tx.tx_id.to_string())
+                    .add_attribute("sender", info.sender)
+                ).set_data(to_json_binary(&exec::tx_resp(deps.as_ref(), fcross_tx.tx_id, None)?)?))
+            }
+            exec::execute_tx(deps, &env, &fcross_tx)
+        },
+        ExecuteBatch{ txs } => {
+            if utils::is_paused(deps.as_ref())? {
+                let items = txs.iter().map(|tx| exec::tx_resp(deps.as_ref(), tx.tx_id, None)).collect::<StdResult<Vec<_>>>()?;
+                return Ok(Response::new().add_event(
+                    utils::paused_rejection_event("execute_batch")
+                    .add_attribute("tx_ids", utils::tx_ids_format(&txs.iter().map(|tx| tx.tx_id).collect::<Vec<u32>>()))
+                    .add_attribute("sender", info.sender)
+                ).set_data(to_json_binary(&ExecuteBatchResp{ items })?))
+            }
+            exec::execute_batch(deps, &env, &txs)
+        },
         FinalizeTx{ instruction } => {
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+            let instruction = Instruction{ decision_hash: None, ..instruction };
             let (attrs, msgs) = exec::finalize_tx(&mut deps, &env, &instruction)?;
-            Ok(Response::new().add_attributes(attrs).add_messages(msgs))
+            Ok(Response::new().add_attributes(attrs).add_submessages(msgs))
         },
+        Pause{} => exec::set_paused(deps, &info, true),
+        Resume{} => exec::set_paused(deps, &info, false),
+        SetCoordinatorPort{ port_id } => exec::set_coordinator_port(deps, &info, port_id),
+        ApproveRebind{} => exec::approve_rebind(deps, &info),
+        Deposit{} => exec::deposit(deps, &info),
+        Withdraw{ amount, recipient } => exec::withdraw(deps, &env, &info, amount, recipient),
+        Subscribe{ contract } => exec::subscribe(deps, &info, contract),
+        Unsubscribe{ contract } => exec::unsubscribe(deps, &info, contract),
+        Transfer{ recipient, amount } => exec::transfer(deps, &info, recipient, amount),
     }
 }
 
 pub mod exec {
-    use crate::{error::ContractError, msg::{FcrossTx, Instruction}, state::{MAX_PENDING_LEN, MY_CHANNEL}, utils};
+    use cosmwasm_std::{Addr, BankMsg, Coin, Event, SubMsg, Uint128, WasmMsg};
+
+    use crate::{error::ContractError, msg::{FcrossTx, Instruction}, state::{MAX_PENDING_LEN, MF_ABORT_REASON_MAP, MF_DECISION_HASH_MAP, MF_DECISION_MAP, MF_FAILURE_REASON_MAP, MF_SENT_VOTE_MAP, MF_VOTE_ACK_MAP, MY_CHANNEL, REBIND_APPROVED}};
 
     use super::*;
-    use crate::msg::{Operation, Vote};
+    use std::collections::BTreeMap;
 
-    #[derive(Debug, Clone, Copy)]
-    pub enum ExecutionStatus {
-        Success,
-        Failure,
-        Uncertainty,
-    }
+    use crate::msg::{AbortReason, CallbackMsg, ExecuteBatchResp, ExecuteTxResp, ExecutionStatus, Operation, OperationQueryMsg, OperationResp, Vote, Votes};
+    use crate::state::{CALLBACK_GAS_LIMIT, CALLBACK_REPLY_ID, MAX_CUSTOM_QUERIES, MAX_SUBSCRIBERS, MF_DIGEST_MAP, MF_EARLY_DECISION_MAP, SUBSCRIBERS, TX_CALLBACK_MAP};
+
+    /// attributes and messages produced by handlers shared between execute and ibc entry points
+    pub type HandlerResult = (Vec<(String, String)>, Vec<SubMsg>);
 
-    pub fn check_execution_stautus(values: &Vec<Option<i64>>) -> ExecutionStatus{
+    pub fn check_execution_stautus<T>(values: &[Option<T>]) -> ExecutionStatus{
         let has_some = values.iter().any(|v| v.is_some());
         let has_none = values.iter().any(|v| v.is_none());
         match (has_some, has_none) {
@@ -101,17 +418,123 @@ pub mod exec {
         }
     }
 
-    pub fn give_vote(tx_id: u32, chain_id: u16, status: ExecutionStatus, channel_id: String, env: &Env) -> StdResult<IbcMsg>{
-        // must eliminate case ExecutionStatus::Uncertainty before entering the function
-        let my_vote = Vote{
-            tx_id,
-            chain_id,
-            success: match status {
-                ExecutionStatus::Success=>true,
-                ExecutionStatus::Failure=>false,
-                _=>unreachable!(),
-            },
+    // apply the operation on every future, None marks a failed branch
+    pub fn apply_operation(deps: Deps, values: &[Option<Balances>], operation: &Operation) -> StdResult<Vec<Option<Balances>>> {
+        // futures often share balances, a custom contract is asked once per distinct balances
+        let mut results: BTreeMap<&Balances, Option<Balances>> = BTreeMap::new();
+        let mut queries = 0;
+        values
+        .iter()
+        .map(|item|{
+            let old = match item {
+                None => return Ok(None),
+                Some(old) => old,
+            };
+            if let Some(result) = results.get(old) {
+                return Ok(result.clone())
+            }
+            let result = match operation {
+                // checked math, an overflow only fails its branch
+                Operation::DebitBalance { denom, amount } => {
+                    utils::update_denom(old, denom, |v| v.checked_sub(*amount).ok())
+                },
+                Operation::CreditBalance { denom, amount } => {
+                    utils::update_denom(old, denom, |v| v.checked_add(*amount).ok())
+                },
+                Operation::Set { denom, value } => utils::update_denom(old, denom, |_| Some(*value)),
+                Operation::CompareAndSet { denom, expected, value } => {
+                    utils::update_denom(old, denom, |v| (v == *expected).then_some(*value))
+                },
+                Operation::DebitIfAbove { denom, amount, floor } => {
+                    utils::update_denom(old, denom, |v| v.checked_sub(*amount).ok().filter(|v| v >= floor))
+                },
+                Operation::CreditCapped { denom, amount, cap } => {
+                    utils::update_denom(old, denom, |v| v.checked_add(*amount).ok().filter(|v| v <= cap))
+                },
+                Operation::Assert { denom, min, max } => {
+                    let v = utils::amount_of(old, denom);
+                    (*min <= v && v <= *max).then(|| old.clone())
+                },
+                Operation::Transfer { from, to, amount } => {
+                    utils::update_denom(old, from, |v| v.checked_sub(*amount).ok())
+                    .and_then(|b| utils::update_denom(&b, to, |v| v.checked_add(*amount).ok()))
+                },
+                // a query error must not abort the tx, the sequence would be stuck at this tx id
+                Operation::Custom { .. } if queries >= MAX_CUSTOM_QUERIES => None,
+                Operation::Custom { contract, payload } => {
+                    queries += 1;
+                    deps.querier.query_wasm_smart::<OperationResp>(
+                        contract,
+                        &OperationQueryMsg::ApplyOperation { balances: old.clone(), payload: payload.clone() },
+                    ).ok().and_then(|resp| resp.balances)
+                },
+            };
+            results.insert(old, result.clone());
+            Ok(result)
+        })
+        .collect::<StdResult<Vec<Option<Balances>>>>()
+    }
+
+    // why the tx fails, taken from the first branch the operation itself fails, None if no branch fails
+    pub fn failure_reason(values: &[Option<Balances>], results: &[Option<Balances>], operation: &Operation) -> Option<AbortReason> {
+        let failed = values.iter().zip(results).find_map(|pair| match pair {
+            (Some(old), None) => Some(old),
+            _ => None,
+        });
+        let old = match failed {
+            Some(old) => old,
+            None => return results.iter().any(|v| v.is_none()).then_some(AbortReason::PriorFailure),
         };
+        Some(match operation {
+            Operation::DebitBalance { .. } => AbortReason::InsufficientBalance,
+            Operation::CreditBalance { .. } => AbortReason::Overflow,
+            Operation::DebitIfAbove { denom, amount, .. } if utils::amount_of(old, denom) < *amount => AbortReason::InsufficientBalance,
+            Operation::CreditCapped { denom, amount, .. } if utils::amount_of(old, denom).checked_add(*amount).is_err() => AbortReason::Overflow,
+            Operation::Transfer { from, amount, .. } if utils::amount_of(old, from) < *amount => AbortReason::InsufficientBalance,
+            Operation::Transfer { .. } => AbortReason::Overflow,
+            Operation::Custom { .. } => AbortReason::CustomRejected,
+            _ => AbortReason::PreconditionFailed,
+        })
+    }
+
+    // reject malformed operations before they reach the futures
+    pub fn validate_operation(deps: Deps, operation: &Operation) -> Result<(), ContractError> {
+        match operation {
+            Operation::CreditBalance { denom, .. }
+            | Operation::DebitBalance { denom, .. }
+            | Operation::Set { denom, .. }
+            | Operation::CompareAndSet { denom, .. }
+            | Operation::DebitIfAbove { denom, .. }
+            | Operation::CreditCapped { denom, .. }
+            | Operation::Assert { denom, .. } => {
+                if denom.is_empty() {
+                    return Err(ContractError::EmptyDenom {})
+                }
+            },
+            Operation::Transfer { from, to, .. } => {
+                if from.is_empty() || to.is_empty() {
+                    return Err(ContractError::EmptyDenom {})
+                }
+            },
+            Operation::Custom { contract, .. } => {
+                deps.api.addr_validate(contract)?;
+            },
+        }
+        if let Operation::Assert { min, max, .. } = *operation {
+            if min > max {
+                return Err(ContractError::InvalidRange { min, max })
+            }
+        }
+        Ok(())
+    }
+
+    pub fn give_vote(deps: Deps, tx_id: u32, status: ExecutionStatus, channel_id: String, env: &Env) -> StdResult<IbcMsg>{
+        // must eliminate case ExecutionStatus::Uncertainty before entering the function
+        let my_vote = make_vote(deps, tx_id, match status {
+            ExecutionStatus::Success=>true,
+            ExecutionStatus::Failure=>false,
+            _=>unreachable!(),
+        })?;
         let msg = IbcMsg::SendPacket {
             channel_id,
             data: to_json_binary(&my_vote)?,
@@ -120,52 +543,71 @@ pub mod exec {
         Ok(msg)
     }
 
-    pub fn execute_tx(
-        deps: DepsMut,
-        env: &Env,
+    pub fn make_vote(deps: Deps, tx_id: u32, success: bool) -> StdResult<Vote> {
+        Ok(Vote{
+            tx_id,
+            chain_id: CHAIN_ID.load(deps.storage)?,
+            success,
+            digest: MF_DIGEST_MAP.may_load(deps.storage, tx_id)?,
+            abort_reason: match success {
+                true => None,
+                false => MF_FAILURE_REASON_MAP.may_load(deps.storage, tx_id)?,
+            },
+        })
+    }
+
+    // the channel votes go out on, None while it is set up or after it closed
+    pub fn vote_channel(deps: Deps) -> StdResult<Option<String>> {
+        Ok(MY_CHANNEL
+            .may_load(deps.storage)?
+            .filter(|c| c.finalized && !c.closed)
+            .map(|c| c.channel_id))
+    }
+
+    // votes of pending txs the coordinator has not acknowledged, sent again once a channel is bound
+    pub fn resend_votes(deps: Deps, env: &Env, channel_id: &str) -> StdResult<Vec<IbcMsg>> {
+        let mut msgs: Vec<IbcMsg> = Vec::new();
+        for tx_id in PENDING_TX_LIST.load(deps.storage)? {
+            if MF_VOTE_ACK_MAP.may_load(deps.storage, tx_id)?.unwrap_or(false) {
+                continue;
+            }
+            if let Some(success) = MF_SENT_VOTE_MAP.may_load(deps.storage, tx_id)? {
+                let status = if success { ExecutionStatus::Success } else { ExecutionStatus::Failure };
+                msgs.push(give_vote(deps, tx_id, status, channel_id.to_string(), env)?);
+            }
+        }
+        Ok(msgs)
+    }
+
+    // run one tx against the latest mf and record its immediate vote, if any
+    fn run_tx(
+        deps: &mut DepsMut,
         tx: &FcrossTx,
-    ) -> Result<Response, ContractError> {
+    ) -> Result<ExecutionStatus, ContractError> {
         // pre-execution check
         let expected = EXPECTED_TX_ID.load(deps.storage)?;
         if tx.tx_id != expected{
             return Err(ContractError::MismatchedExecutionTxId { sent_id: tx.tx_id, expected_id: expected })
         }
         let mut pending = PENDING_TX_LIST.load(deps.storage)?;
-        let len = match pending.len() {
-            0 => 0,
-            _ => expected-pending[0],
-        };
+        let len = utils::calculate_pending_list_len(deps.as_ref())?;
         if len>MAX_PENDING_LEN{
             return Err(ContractError::UpperBound { max_length: MAX_PENDING_LEN })
         }
 
+        validate_operation(deps.as_ref(), &tx.operation)?;
+        let callback = tx.callback.as_ref().map(|c| deps.api.addr_validate(c)).transpose()?;
+
         // execution
         let mut old_values = MF_MAP.load(deps.storage, tx.tx_id-1)?;
-        let new_values = old_values
-        .iter()
-        .map(|&item|{
-            match item{
-                None => None,
-                Some(old_value) => {
-                    match tx.operation {
-                        Operation::DebitBalance { amount } => {
-                            if old_value>=amount{
-                                Some(old_value-amount)
-                            } else {
-                                None
-                            }
-                        },
-                        Operation::CreditBalance { amount } => {
-                            Some(old_value+amount)
-                        },
-                    }
-                }
-            }
-        })
-        .collect::<Vec<Option<i64>>>();
+        let new_values = apply_operation(deps.as_ref(), &old_values, &tx.operation)?;
 
         // check if we can give instant voting
         let status = check_execution_stautus(&new_values);
+        // kept for a failure vote given now or after pending txs finalize
+        if let Some(reason) = failure_reason(&old_values, &new_values, &tx.operation) {
+            MF_FAILURE_REASON_MAP.save(deps.storage, tx.tx_id, &reason)?;
+        }
 
         // post execution update
         old_values.extend(new_values);
@@ -174,17 +616,55 @@ pub mod exec {
             ExecutionStatus::Success | ExecutionStatus::Failure => &true,
             ExecutionStatus::Uncertainty => &false,
         })?;
+        if !matches!(status, ExecutionStatus::Uncertainty) {
+            MF_SENT_VOTE_MAP.save(deps.storage, tx.tx_id, &matches!(status, ExecutionStatus::Success))?;
+        }
         EXPECTED_TX_ID.save(deps.storage, &(expected+1))?;
         pending.push(expected);
         PENDING_TX_LIST.save(deps.storage, &pending)?;
+        if let Some(callback) = &callback {
+            TX_CALLBACK_MAP.save(deps.storage, tx.tx_id, callback)?;
+        }
+        MF_DIGEST_MAP.save(deps.storage, tx.tx_id, &utils::tx_digest(tx)?)?;
+        Ok(status)
+    }
+
+    // response data of a tx, status is None if it was turned away while paused
+    pub fn tx_resp(deps: Deps, tx_id: u32, status: Option<ExecutionStatus>) -> StdResult<ExecuteTxResp> {
+        Ok(ExecuteTxResp{
+            tx_id,
+            status,
+            rejected: status.is_none(),
+            pending_depth: PENDING_TX_LIST.load(deps.storage)?.len() as u32,
+        })
+    }
+
+    pub fn execute_tx(
+        mut deps: DepsMut,
+        env: &Env,
+        tx: &FcrossTx,
+    ) -> Result<Response, ContractError> {
+        let status = run_tx(&mut deps, tx)?;
+        let result = tx_resp(deps.as_ref(), tx.tx_id, Some(status))?;
+
+        // decided before it executed here, the vote is no longer needed
+        if let Some((attrs, msgs)) = apply_early_decision(&mut deps, env, tx.tx_id)? {
+            return Ok(Response::new()
+            .add_attribute("voted", "false")
+            .add_attributes(attrs)
+            .add_submessages(msgs)
+            .add_attribute("executed_tx", tx.tx_id.to_string())
+            .set_data(to_json_binary(&result)?))
+        }
 
         // response
         let reps = match status {
             ExecutionStatus::Success | ExecutionStatus::Failure => {
-                let msg = give_vote(tx.tx_id, CHAIN_ID.load(deps.storage)?, status, MY_CHANNEL.load(deps.storage)?.channel_id, env)?;
-                Response::new()
-                .add_message(msg)
-                .add_attribute("voted", "true")
+                let resp = Response::new().add_attribute("voted", "true");
+                match vote_channel(deps.as_ref())? {
+                    Some(channel_id) => resp.add_message(give_vote(deps.as_ref(), tx.tx_id, status, channel_id, env)?),
+                    None => resp.add_attribute("vote_deferred", "no open channel"),
+                }
             },
             ExecutionStatus::Uncertainty => {
                 Response::new()
@@ -192,17 +672,305 @@ pub mod exec {
             },
         };
         Ok(reps
-            .add_attribute("executed_tx", tx.tx_id.to_string()))
+            .add_attribute("executed_tx", tx.tx_id.to_string())
+            .set_data(to_json_binary(&result)?))
+    }
+
+    // consecutive txs in one call, every immediate vote goes out in a single packet
+    pub fn execute_batch(
+        mut deps: DepsMut,
+        env: &Env,
+        txs: &[FcrossTx],
+    ) -> Result<Response, ContractError> {
+        if txs.is_empty() {
+            return Err(ContractError::EmptyBatch {})
+        }
+        let mut items: Vec<ExecuteTxResp> = Vec::new();
+        let mut votes: Vec<Vote> = Vec::new();
+        let mut attrs: Vec<(String, String)> = Vec::new();
+        let mut msgs: Vec<SubMsg> = Vec::new();
+        for tx in txs {
+            let status = run_tx(&mut deps, tx)?;
+            items.push(tx_resp(deps.as_ref(), tx.tx_id, Some(status))?);
+            if let Some((a, m)) = apply_early_decision(&mut deps, env, tx.tx_id)? {
+                attrs.extend(a);
+                msgs.extend(m);
+            } else if !matches!(status, ExecutionStatus::Uncertainty) {
+                votes.push(make_vote(deps.as_ref(), tx.tx_id, matches!(status, ExecutionStatus::Success))?);
+            }
+        }
+
+        let mut resp = Response::new()
+        .add_attributes(attrs)
+        .add_submessages(msgs)
+        .add_attribute("executed_txs", utils::tx_ids_format(&txs.iter().map(|tx| tx.tx_id).collect::<Vec<u32>>()))
+        .add_attribute("voted_txs", votes.len().to_string());
+        if !votes.is_empty() {
+            match vote_channel(deps.as_ref())? {
+                Some(channel_id) => {
+                    resp = resp.add_message(IbcMsg::SendPacket {
+                        channel_id,
+                        data: to_json_binary(&Votes{ items: votes })?,
+                        timeout: utils::get_timeout(env),
+                    })
+                },
+                None => resp = resp.add_attribute("vote_deferred", "no open channel"),
+            }
+        }
+        Ok(resp.set_data(to_json_binary(&ExecuteBatchResp{ items })?))
+    }
+
+    pub fn set_paused(
+        deps: DepsMut,
+        info: &MessageInfo,
+        paused: bool,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        PAUSED.save(deps.storage, &paused)?;
+        let event = Event::new(if paused { "paused" } else { "resumed" })
+        .add_attribute("admin", info.sender.clone());
+        Ok(Response::new().add_event(event))
+    }
+
+    pub fn set_coordinator_port(
+        deps: DepsMut,
+        info: &MessageInfo,
+        port_id: String,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        COORDINATOR_PORT.save(deps.storage, &port_id)?;
+        Ok(Response::new()
+        .add_attribute("method", "set_coordinator_port")
+        .add_attribute("coordinator_port", port_id))
+    }
+
+    pub fn approve_rebind(
+        deps: DepsMut,
+        info: &MessageInfo,
+    ) -> Result<Response, ContractError> {
+        utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        REBIND_APPROVED.save(deps.storage, &true)?;
+        Ok(Response::new()
+        .add_event(Event::new("rebind_approved").add_attribute("admin", info.sender.clone())))
+    }
+
+    // local operations are ordered after every executed tx, so only the latest mf changes.
+    // they must hold in every future that has not already failed, otherwise a given vote could break
+    fn update_head(deps: DepsMut, operation: &Operation) -> StdResult<bool> {
+        let head = EXPECTED_TX_ID.load(deps.storage)?-1;
+        let old_values = MF_MAP.load(deps.storage, head)?;
+        let values = apply_operation(deps.as_ref(), &old_values, operation)?;
+        if old_values.iter().zip(values.iter()).any(|(old, new)| old.is_some() && new.is_none()) {
+            return Ok(false)
+        }
+        MF_MAP.save(deps.storage, head, &values)?;
+        Ok(true)
+    }
+
+    pub fn deposit(
+        deps: DepsMut,
+        info: &MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let denom = ESCROW_DENOM.may_load(deps.storage)?.ok_or(ContractError::EscrowNotConfigured {})?;
+        let paid = cw_utils::must_pay(info, &denom)?;
+        // a credit only fails a branch on overflow
+        let account = utils::account_key(&info.sender, &denom);
+        if !update_head(deps, &Operation::CreditBalance { denom: account, amount: paid })? {
+            return Err(ContractError::InvalidAmount { amount: paid.to_string() })
+        }
+        Ok(Response::new()
+        .add_attribute("method", "deposit")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("amount", paid))
+    }
+
+    pub fn withdraw(
+        deps: DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let denom = ESCROW_DENOM.may_load(deps.storage)?.ok_or(ContractError::EscrowNotConfigured {})?;
+        if amount.is_zero() {
+            return Err(ContractError::InvalidAmount { amount: amount.to_string() })
+        }
+        let recipient = match recipient {
+            Some(recipient) => deps.api.addr_validate(&recipient)?,
+            None => info.sender.clone(),
+        };
+
+        // committed credits from other chains are not backed by coins held here
+        let held = deps.querier.query_balance(&env.contract.address, &denom)?.amount;
+        if amount > held {
+            return Err(ContractError::InsufficientEscrow { requested: amount, held })
+        }
+        let account = utils::account_key(&info.sender, &denom);
+        let head = EXPECTED_TX_ID.load(deps.storage)?-1;
+        let available = utils::certain_value(&MF_MAP.load(deps.storage, head)?, &account).unwrap_or_default();
+        if !update_head(deps, &Operation::DebitBalance { denom: account, amount })? {
+            return Err(ContractError::InsufficientCertainBalance { requested: amount, available })
+        }
+        Ok(Response::new()
+        .add_message(BankMsg::Send { to_address: recipient.to_string(), amount: vec![Coin{ denom, amount }] })
+        .add_attribute("method", "withdraw")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount))
+    }
+
+    pub fn transfer(
+        deps: DepsMut,
+        info: &MessageInfo,
+        recipient: String,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let denom = ESCROW_DENOM.may_load(deps.storage)?.ok_or(ContractError::EscrowNotConfigured {})?;
+        if amount.is_zero() {
+            return Err(ContractError::InvalidAmount { amount: amount.to_string() })
+        }
+        let recipient = deps.api.addr_validate(&recipient)?;
+        let from = utils::account_key(&info.sender, &denom);
+        let head = EXPECTED_TX_ID.load(deps.storage)?-1;
+        let available = utils::certain_value(&MF_MAP.load(deps.storage, head)?, &from).unwrap_or_default();
+        // ordered after every executed tx like deposits, so it cannot break a pending tx
+        let operation = Operation::Transfer { from, to: utils::account_key(&recipient, &denom), amount };
+        if !update_head(deps, &operation)? {
+            return Err(ContractError::InsufficientCertainBalance { requested: amount, available })
+        }
+        Ok(Response::new()
+        .add_attribute("action", "transfer")
+        .add_attribute("from", info.sender.clone())
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount))
+    }
+
+    pub fn subscribe(
+        deps: DepsMut,
+        info: &MessageInfo,
+        contract: String,
+    ) -> Result<Response, ContractError> {
+        // a contract subscribes itself, the admin can subscribe others and remove any of them
+        let contract = deps.api.addr_validate(&contract)?;
+        if info.sender != contract {
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        }
+        let count = SUBSCRIBERS.keys(deps.storage, None, None, cosmwasm_std::Order::Ascending).count() as u32;
+        if !SUBSCRIBERS.has(deps.storage, &contract) && count >= MAX_SUBSCRIBERS {
+            return Err(ContractError::TooManySubscribers { max: MAX_SUBSCRIBERS })
+        }
+        SUBSCRIBERS.save(deps.storage, &contract, &cosmwasm_std::Empty {})?;
+        Ok(Response::new()
+        .add_attribute("method", "subscribe")
+        .add_attribute("subscriber", contract))
+    }
+
+    pub fn unsubscribe(
+        deps: DepsMut,
+        info: &MessageInfo,
+        contract: String,
+    ) -> Result<Response, ContractError> {
+        let contract = deps.api.addr_validate(&contract)?;
+        if info.sender != contract {
+            utils::ensure_admin(deps.as_ref(), &info.sender)?;
+        }
+        SUBSCRIBERS.remove(deps.storage, &contract);
+        Ok(Response::new()
+        .add_attribute("method", "unsubscribe")
+        .add_attribute("subscriber", contract))
+    }
+
+    // one callback per contract, replies only on error so a failing contract cannot revert the finalization,
+    // the gas limit keeps a contract from failing the whole packet by running out of gas
+    fn finalization_callbacks(deps: &mut DepsMut, instruction: &Instruction) -> StdResult<Vec<SubMsg>> {
+        let mut targets = SUBSCRIBERS
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+        if let Some(callback) = TX_CALLBACK_MAP.may_load(deps.storage, instruction.tx_id)? {
+            TX_CALLBACK_MAP.remove(deps.storage, instruction.tx_id);
+            if !targets.contains(&callback) {
+                targets.push(callback);
+            }
+        }
+        let msg = to_json_binary(&CallbackMsg::FcrossFinalized {
+            tx_id: instruction.tx_id,
+            commitment: instruction.commitment,
+            abort_reason: instruction.abort_reason,
+        })?;
+        Ok(targets
+        .into_iter()
+        .map(|contract| SubMsg::reply_on_error(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: msg.clone(),
+            funds: vec![],
+        }, CALLBACK_REPLY_ID).with_gas_limit(CALLBACK_GAS_LIMIT))
+        .collect())
+    }
+
+    // an instruction that does not apply is only logged so the rest of the batch still finalizes
+    pub fn finalize_txs(
+        deps: &mut DepsMut,
+        env: &Env,
+        instructions: &[Instruction],
+    ) -> StdResult<HandlerResult> {
+        let mut attrs: Vec<(String, String)> = Vec::new();
+        let mut msgs: Vec<SubMsg> = Vec::new();
+        for instruction in instructions {
+            match finalize_tx(deps, env, instruction) {
+                Ok((a, m)) => {
+                    attrs.extend(a);
+                    msgs.extend(m);
+                },
+                Err(e) => {
+                    let mut logs = MY_LOGS.load(deps.storage)?;
+                    logs.push_str(format!("\n{:?}", e).as_str());
+                    MY_LOGS.save(deps.storage, &logs)?;
+                },
+            }
+        }
+        Ok((attrs, msgs))
+    }
+
+    // finalize a tx right after it executed if its decision arrived first
+    fn apply_early_decision(deps: &mut DepsMut, env: &Env, tx_id: u32) -> Result<Option<HandlerResult>, ContractError> {
+        let instruction = match MF_EARLY_DECISION_MAP.may_load(deps.storage, tx_id)? {
+            Some(instruction) => instruction,
+            None => return Ok(None),
+        };
+        MF_EARLY_DECISION_MAP.remove(deps.storage, tx_id);
+        let (mut attrs, msgs) = finalize_tx(deps, env, &instruction)?;
+        attrs.push(("early_decision".to_string(), tx_id.to_string()));
+        Ok(Some((attrs, msgs)))
+    }
+
+    fn effective_instruction(deps: Deps, instruction: &Instruction) -> StdResult<Instruction> {
+        let chain_id = CHAIN_ID.load(deps.storage)?;
+        if !instruction.commitment || !instruction.aborted_chains.contains(&chain_id) {
+            return Ok(instruction.clone())
+        }
+        Ok(Instruction{
+            commitment: false,
+            abort_reason: MF_FAILURE_REASON_MAP.may_load(deps.storage, instruction.tx_id)?,
+            ..instruction.clone()
+        })
     }
 
     pub fn finalize_tx(
         deps: &mut DepsMut,
         env: &Env,
         instruction: &Instruction,
-    ) -> Result<(Vec<(String, String)>, Vec<IbcMsg>), ContractError> {
+    ) -> Result<HandlerResult, ContractError> {
+        // a decision can overtake the tx, keep it until the tx executes
+        let expected = EXPECTED_TX_ID.load(deps.storage)?;
+        if instruction.tx_id >= expected {
+            MF_EARLY_DECISION_MAP.save(deps.storage, instruction.tx_id, instruction)?;
+            return Ok((vec![("early_decision_stored".to_string(), instruction.tx_id.to_string())], Vec::new()))
+        }
+
+        // the decision that applies to this chain, a commit may abort only our part
+        let instruction = &effective_instruction(deps.as_ref(), instruction)?;
+
         // pre-finalization check
         let mut pending = PENDING_TX_LIST.load(deps.storage)?;
-        let expected = EXPECTED_TX_ID.load(deps.storage)?;
         let pos = match pending.iter().position(|&x| {x==instruction.tx_id}) {
             Some(i) => i,
             None => return Err(ContractError::MismatchedFinalizationTxId { sent_id: instruction.tx_id, expected_id: pending }) 
@@ -213,36 +981,37 @@ pub mod exec {
             true => 1,
             false => 0,
         };
-        let chain_id = CHAIN_ID.load(deps.storage)?;
-        let channel_id = MY_CHANNEL.load(deps.storage)?.channel_id;
-        let mut msgs: Vec<IbcMsg> = Vec::new();
+        let channel_id = vote_channel(deps.as_ref())?;
+        let mut msgs: Vec<SubMsg> = Vec::new();
         let mut attrs: Vec<(String, String)> = vec![("finalized_tx".to_string(), instruction.tx_id.to_string()), ("committed".to_string(), instruction.commitment.to_string())];
-        let mut shift = pos;
         for i in instruction.tx_id..expected {
             // update mfs
             let updated_mf = MF_MAP.load(deps.storage, i)?
-            .iter()
+            .into_iter()
             .enumerate()
-            .filter(|(j,_)|{(j >> shift) & 1 == n})
-            .map(|(_, &v)| v)
-            .collect::<Vec<Option<i64>>>();
+            // the finalized tx keeps bit position pos in every later mf
+            .filter(|(j,_)|{(j >> pos) & 1 == n})
+            .map(|(_, v)| v)
+            .collect::<Vec<Option<Balances>>>();
             MF_MAP.save(deps.storage, i, &updated_mf)?;
-            shift += 1;
 
-            // vote check
+            // vote check, tx i is the highest bit of its own mf so only the committed half matters.
+            // a tx finalized before it could vote stays unvoted and must not vote anymore
             let voted = MF_VOTE_MAP.load(deps.storage, i)?;
-            if !voted {
-                let status = check_execution_stautus(&updated_mf);
+            if !voted && i != instruction.tx_id && pending.contains(&i) {
+                let status = check_execution_stautus(&updated_mf[updated_mf.len()/2..]);
                 match status {
                     ExecutionStatus::Success | ExecutionStatus::Failure => {
-                        let msg = give_vote(i, chain_id, status, channel_id.clone(), env)?;
-                        msgs.push(msg);
+                        if let Some(channel_id) = &channel_id {
+                            msgs.push(SubMsg::new(give_vote(deps.as_ref(), i, status, channel_id.clone(), env)?));
+                        }
                         attrs.push((format!("newly_voted_tx_{}", i), match status {
                             ExecutionStatus::Success=> "success".to_string(),
                             ExecutionStatus::Failure=> "failure".to_string(),
                             _=>unreachable!(),
                         }));
                         MF_VOTE_MAP.save(deps.storage, i, &true)?;
+                        MF_SENT_VOTE_MAP.save(deps.storage, i, &matches!(status, ExecutionStatus::Success))?;
                     },
                     ExecutionStatus::Uncertainty => {},
                 }
@@ -252,6 +1021,14 @@ pub mod exec {
         // post-finalization update
         pending.remove(pos);
         PENDING_TX_LIST.save(deps.storage, &pending)?;
+        MF_DECISION_MAP.save(deps.storage, instruction.tx_id, &instruction.commitment)?;
+        if let Some(decision_hash) = &instruction.decision_hash {
+            MF_DECISION_HASH_MAP.save(deps.storage, instruction.tx_id, decision_hash)?;
+        }
+        if let Some(reason) = &instruction.abort_reason {
+            MF_ABORT_REASON_MAP.save(deps.storage, instruction.tx_id, reason)?;
+        }
+        msgs.extend(finalization_callbacks(deps, instruction)?);
 
         // resp
         Ok((attrs, msgs))
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, StdError};
+use cosmwasm_std::{Addr, StdError, Uint128};
 use cw_utils::PaymentError;
 use thiserror::Error;
 
@@ -21,9 +21,71 @@ pub enum ContractError {
     UpperBound{
         max_length: u32,
     },
+    #[error("batch contains no tx")]
+    EmptyBatch {},
+    #[error("operation denom must not be empty")]
+    EmptyDenom {},
+    #[error("operation range min {min} is above max {max}")]
+    InvalidRange {
+        min: Uint128,
+        max: Uint128,
+    },
     
+    #[error("expect ibc channel version {expected} but got {version}")]
+    InvalidIbcVersion {
+        version: String,
+        expected: String,
+    },
+    #[error("only unordered ibc channels are supported")]
+    InvalidIbcOrder {},
+    #[error("counterparty port {port_id} is not the configured coordinator port")]
+    InvalidCounterpartyPort {
+        port_id: String,
+    },
+
     #[error("{sender} is not contract admin")]
     Unauthorized { sender: Addr },
     #[error("Payment error: {0}")]
     PaymentError(#[from] PaymentError),
-}
\ No newline at end of file
+    #[error("reach maximum number of subscribers {max}")]
+    TooManySubscribers {
+        max: u32,
+    },
+    #[error("no escrow denom is configured")]
+    EscrowNotConfigured {},
+    #[error("amount {amount} is zero or out of the ledger range")]
+    InvalidAmount {
+        amount: String,
+    },
+    #[error("debit of {requested} exceeds the certain balance {available}")]
+    InsufficientCertainBalance {
+        requested: Uint128,
+        available: Uint128,
+    },
+    #[error("instantiate needs {expected} of the escrow denom attached, got {received}")]
+    EscrowFundsMismatch {
+        expected: Uint128,
+        received: Uint128,
+    },
+    #[error("payout of {requested} exceeds the escrow held {held}")]
+    InsufficientEscrow {
+        requested: Uint128,
+        held: Uint128,
+    },
+    #[error("cannot migrate from contract {previous_contract}")]
+    InvalidMigrationContract {
+        previous_contract: String,
+    },
+    #[error("cannot migrate from version {previous_version} to older version {new_version}")]
+    InvalidMigrationVersion {
+        previous_version: String,
+        new_version: String,
+    },
+    #[error("legacy_denom or an escrow denom is needed to migrate i64 values")]
+    MissingLegacyDenom {},
+    #[error("committed value {value} of tx {tx_id} is negative and cannot be migrated")]
+    NegativeCommittedValue {
+        tx_id: u32,
+        value: i64,
+    },
+}
@@ -0,0 +1,63 @@
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, CosmosMsg, QuerierWrapper, Reply, StdError, StdResult, WasmMsg, WasmQuery
+};
+
+use crate::msg::{ExecuteMsg, ExecuteTxResp, FcrossTx, Operation, PipelineStateResp, QueryMsg, TxStatusResp};
+
+/// typed access to an mf1 instance for contracts built against the `library` feature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mf1Contract(pub Addr);
+
+impl Mf1Contract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_json_binary(&msg.into())?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    /// submit a tx, `callback` is told about the finalization
+    pub fn execute_tx(&self, tx_id: u32, operation: Operation, callback: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ExecuteTx { fcross_tx: FcrossTx { tx_id, operation, callback } })
+    }
+
+    pub fn execute_batch(&self, txs: Vec<FcrossTx>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ExecuteBatch { txs })
+    }
+
+    fn query<T: serde::de::DeserializeOwned>(&self, querier: &QuerierWrapper, msg: &QueryMsg) -> StdResult<T> {
+        querier.query(&WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_json_binary(msg)?,
+        }
+        .into())
+    }
+
+    /// the tx id the next ExecuteTx must carry
+    pub fn expected_tx_id(&self, querier: &QuerierWrapper) -> StdResult<u32> {
+        Ok(self.pipeline_state(querier)?.expected_tx_id)
+    }
+
+    pub fn pipeline_state(&self, querier: &QuerierWrapper) -> StdResult<PipelineStateResp> {
+        self.query(querier, &QueryMsg::PipelineState {})
+    }
+
+    pub fn tx_status(&self, querier: &QuerierWrapper, tx_id: u32) -> StdResult<TxStatusResp> {
+        self.query(querier, &QueryMsg::TxStatus { tx_id })
+    }
+}
+
+/// decode the data of an ExecuteTx sent as a submessage with a reply on success
+pub fn parse_execute_tx_reply(msg: Reply) -> StdResult<ExecuteTxResp> {
+    let res = cw_utils::parse_reply_execute_data(msg).map_err(|e| StdError::generic_err(e.to_string()))?;
+    match res.data {
+        Some(data) => from_json(&data),
+        None => Err(StdError::generic_err("no execute_tx data in reply")),
+    }
+}
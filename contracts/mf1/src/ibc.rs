@@ -1,51 +1,92 @@
-use cosmwasm_std::{ensure, entry_point, DepsMut, Env, StdError, StdResult, from_json};
-use cosmwasm_std::{IbcBasicResponse, IbcChannelConnectMsg, IbcChannelCloseMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketReceiveMsg, IbcReceiveResponse, IbcPacketAckMsg, IbcPacketTimeoutMsg, StdAck};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{ensure, DepsMut, Env, StdError, StdResult, from_json};
+use cosmwasm_std::{Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel, IbcChannelConnectMsg, IbcChannelCloseMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketReceiveMsg, IbcReceiveResponse, IbcPacketAckMsg, IbcPacketTimeoutMsg, StdAck};
 
-use crate::msg::Instruction;
-use crate::state::{ChannelInfo, MY_CHANNEL, MY_LOGS};
-use crate::contract::exec::finalize_tx;
+use crate::msg::{Instruction, Instructions, Vote, Votes};
+use crate::state::{ChannelInfo, COORDINATOR_PORT, MF_VOTE_ACK_MAP, MY_CHANNEL, MY_LOGS, REBIND_APPROVED};
+use crate::contract::exec::{finalize_tx, finalize_txs, resend_votes};
 use crate::error::ContractError;
 
+pub const IBC_APP_VERSION: &str = "myfc-1";
+pub const IBC_ORDERING: IbcOrder = IbcOrder::Unordered;
+
+// check a channel against the protocol and the configured coordinator port
+fn validate_channel(deps: &DepsMut, channel: &IbcChannel, counterparty_version: Option<&str>) -> Result<(), ContractError> {
+    if channel.order != IBC_ORDERING {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    for version in std::iter::once(channel.version.as_str()).chain(counterparty_version) {
+        if version != IBC_APP_VERSION {
+            return Err(ContractError::InvalidIbcVersion { version: version.to_string(), expected: IBC_APP_VERSION.to_string() });
+        }
+    }
+    let port_id = &channel.counterparty_endpoint.port_id;
+    if COORDINATOR_PORT.may_load(deps.storage)?.as_ref() != Some(port_id) {
+        return Err(ContractError::InvalidCounterpartyPort { port_id: port_id.clone() });
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_open(
     deps: DepsMut,
     _env: Env,
     msg: IbcChannelOpenMsg
-) -> StdResult<IbcChannelOpenResponse> {
+) -> Result<IbcChannelOpenResponse, ContractError> {
     // handle msg OpenInit/OpenTry
     let channel = msg.channel();
-    // only support one channel per contract
-    ensure!(MY_CHANNEL.may_load(deps.storage)?.is_none(), StdError::generic_err("channel already exists"));
+    validate_channel(&deps, channel, msg.counterparty_version())?;
+    // only support one channel per contract, unless the admin approved replacing it
+    let rebind = REBIND_APPROVED.may_load(deps.storage)?.unwrap_or(false);
+    ensure!(rebind || MY_CHANNEL.may_load(deps.storage)?.is_none(), StdError::generic_err("channel already exists"));
     MY_CHANNEL.save(deps.storage, &ChannelInfo{
         channel_id: channel.endpoint.channel_id.clone(),
         finalized: false,
+        closed: false,
     })?;
-    Ok(())
+    Ok(Some(Ibc3ChannelOpenResponse{ version: IBC_APP_VERSION.to_string() }))
 }
  
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_connect(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcChannelConnectMsg,
-) -> StdResult<IbcBasicResponse> {
+) -> Result<IbcBasicResponse, ContractError> {
     let channel = msg.channel();
+    validate_channel(&deps, channel, msg.counterparty_version())?;
     let mut channel_info = MY_CHANNEL.load(deps.storage)?;
     ensure!(channel_info.channel_id==channel.endpoint.channel_id, StdError::generic_err("incosistent channel id"));
-    ensure!(channel_info.finalized==false, StdError::generic_err("channel already established"));
+    ensure!(!channel_info.finalized, StdError::generic_err("channel already established"));
     channel_info.finalized=true;
     MY_CHANNEL.save(deps.storage, &channel_info)?;
+    REBIND_APPROVED.remove(deps.storage);
+
+    // votes lost with a previous channel, or given before any channel existed
+    let msgs = resend_votes(deps.as_ref(), &env, &channel_info.channel_id)?;
     Ok(IbcBasicResponse::new()
-    .add_attribute("established_channel", channel_info.channel_id))
+    .add_attribute("established_channel", channel_info.channel_id)
+    .add_attribute("resent_votes", msgs.len().to_string())
+    .add_messages(msgs))
 }
  
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_close(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: IbcChannelCloseMsg,
+    msg: IbcChannelCloseMsg,
 ) -> StdResult<IbcBasicResponse> {
-    Err(StdError::generic_err("closing not allowed"))
+    // keep the ledger, votes wait for an admin approved rebind
+    let channel_id = &msg.channel().endpoint.channel_id;
+    if let Some(mut channel_info) = MY_CHANNEL.may_load(deps.storage)? {
+        if channel_info.channel_id == *channel_id {
+            channel_info.closed = true;
+            MY_CHANNEL.save(deps.storage, &channel_info)?;
+        }
+    }
+    Ok(IbcBasicResponse::new()
+    .add_attribute("closed_channel", channel_id))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -71,22 +112,36 @@ pub fn packet_receive_handler(
     env: Env,
     msg: IbcPacketReceiveMsg,        
 ) -> Result<IbcReceiveResponse, ContractError> {
-    let instruction: Instruction = from_json(&msg.packet.data)?;
-    let (attrs, msgs) = finalize_tx(deps, &env, &instruction)?;
+    // a bare instruction, or the batch of one coordinator flush applied in order
+    let (attrs, msgs) = match from_json::<Instruction>(&msg.packet.data) {
+        Ok(instruction) => finalize_tx(deps, &env, &instruction)?,
+        Err(_) => {
+            let instructions: Instructions = from_json(&msg.packet.data)?;
+            finalize_txs(deps, &env, &instructions.items)?
+        },
+    };
     let ack = StdAck::success(b"mf_success");
-    Ok(IbcReceiveResponse::new().add_attributes(attrs).add_messages(msgs).set_ack(ack))
+    Ok(IbcReceiveResponse::new().add_attributes(attrs).add_submessages(msgs).set_ack(ack))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_packet_ack(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: IbcPacketAckMsg,        
+    msg: IbcPacketAckMsg,        
 ) -> StdResult<IbcBasicResponse> {
+    // the coordinator always acks success, so the votes are delivered
+    let votes = match from_json::<Vote>(&msg.original_packet.data) {
+        Ok(vote) => vec![vote],
+        Err(_) => from_json::<Votes>(&msg.original_packet.data)?.items,
+    };
+    for vote in votes {
+        MF_VOTE_ACK_MAP.save(deps.storage, vote.tx_id, &true)?;
+    }
     Ok(IbcBasicResponse::default())
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_packet_timeout(
     _deps: DepsMut,
     _env: Env,
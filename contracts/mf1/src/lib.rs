@@ -1,7 +1,9 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult
+    Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult
 };
-use msg::{InstantiateMsg, QueryMsg, ExecuteMsg};
+use msg::{InstantiateMsg, QueryMsg, ExecuteMsg, MigrateMsg};
 use error::ContractError;
 
 pub mod contract;
@@ -10,6 +12,10 @@ pub mod state;
 pub mod error;
 pub mod utils;
 pub mod ibc;
+#[cfg(feature = "library")]
+pub mod helpers;
+#[cfg(test)]
+mod tests;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -17,7 +23,7 @@ pub fn instantiate(
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     contract::instantiate(deps, env, info, msg)
 }
 
@@ -35,4 +41,18 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     contract::execute(deps, env, info, msg)
-}
\ No newline at end of file
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    contract::migrate(deps, env, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    contract::reply(deps, env, msg)
+}
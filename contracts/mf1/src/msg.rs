@@ -1,50 +1,211 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary, Coin, Uint128};
+
+use crate::state::{Balances, ChannelInfo, TokenMeta};
 
 /* Initiate */
 #[cw_serde]
 pub struct InstantiateMsg {
     pub chain_id: u16,
-    pub original_value: i64,
+    pub original_balances: Vec<Coin>,
+    /// defaults to the instantiator
+    pub admin: Option<String>,
+    /// ibc port of the coordinator contract, channels are refused until it is set
+    pub coordinator_port: Option<String>,
+    /// native denom held in escrow for the ledger, deposits and withdrawals are disabled without it.
+    /// Its original balance must be attached as funds. Deposits go to the `<address>@<denom>` entry
+    /// of the depositor, which txs can move like any denom. Credits committed by cross-chain txs move
+    /// value whose coins stay on the debited chain, so payouts are capped by the coins held here
+    pub escrow_denom: Option<String>,
+    /// ledger metadata reported by TokenInfo, defaults to the chain id based name
+    pub token: Option<TokenMeta>,
+}
+
+/* Migrate */
+#[cw_serde]
+pub struct MigrateMsg {
+    /// sets the admin, instances from before 0.2.0 have none
+    pub admin: Option<String>,
+    /// denom the i64 values of instances from before 0.3.0 are moved to, defaults to the escrow denom
+    pub legacy_denom: Option<String>,
 }
 
 /* Execute */
 #[cw_serde]
 pub enum ExecuteMsg {
     ExecuteTx { fcross_tx: FcrossTx },
+    /// consecutive txs starting at the expected tx id, immediate votes share one packet
+    ExecuteBatch { txs: Vec<FcrossTx> },
+    /// admin only, finalize without the coordinator channel, the decision hash is only taken from ibc
     FinalizeTx { instruction: Instruction },
+    /// admin only, reject new txs until resumed
+    Pause {},
+    /// admin only
+    Resume {},
+    /// admin only, the port later channel handshakes must come from
+    SetCoordinatorPort { port_id: String },
+    /// admin only, let a new channel to the coordinator port replace the current one
+    ApproveRebind {},
+    /// credit the attached escrow coins to the account of the sender in every future
+    Deposit {},
+    /// pay out escrow coins from the account of the sender, up to the amount certain in every future
+    Withdraw { amount: Uint128, recipient: Option<String> },
+    /// the contract itself or the admin, contract receives a callback for every finalized tx
+    Subscribe { contract: String },
+    /// the subscribed contract itself or the admin, e.g. to free a slot of MAX_SUBSCRIBERS
+    Unsubscribe { contract: String },
+    /// cw20 compatible, move escrow denom value from the account of the sender to the one of recipient
+    /// in every future, so it needs the amount to be certain and keeps every pending tx valid
+    Transfer { recipient: String, amount: Uint128 },
 }
 
 #[cw_serde]
 pub struct FcrossTx{
     pub tx_id: u32,
     pub operation: Operation,
+    /// contract receiving a CallbackMsg once the tx is finalized
+    pub callback: Option<String>,
+}
+
+/// response data of ExecuteTx
+#[cw_serde]
+pub struct ExecuteTxResp {
+    pub tx_id: u32,
+    /// Success or Failure if the vote was given right away, None if rejected
+    pub status: Option<ExecutionStatus>,
+    /// not executed because intake is paused, the tx id is still expected
+    pub rejected: bool,
+    /// number of pending txs including this one
+    pub pending_depth: u32,
+}
+
+/// response data of ExecuteBatch, one entry per tx
+#[cw_serde]
+pub struct ExecuteBatchResp {
+    pub items: Vec<ExecuteTxResp>,
+}
+
+/// sent to callback and subscriber contracts, a failing callback does not affect mf1
+#[cw_serde]
+pub enum CallbackMsg {
+    FcrossFinalized { tx_id: u32, commitment: bool, abort_reason: Option<AbortReason> },
 }
 
 #[cw_serde]
 pub enum Operation {
-    CreditBalance { amount: i64 },
-    DebitBalance { amount: i64 },
+    CreditBalance { denom: String, amount: Uint128 },
+    DebitBalance { denom: String, amount: Uint128 },
+    /// overwrite the amount of denom
+    Set { denom: String, value: Uint128 },
+    /// overwrite the amount of denom, fails unless the current amount is expected
+    CompareAndSet { denom: String, expected: Uint128, value: Uint128 },
+    /// fails if the amount would end up below floor
+    DebitIfAbove { denom: String, amount: Uint128, floor: Uint128 },
+    /// fails if the amount would end up above cap
+    CreditCapped { denom: String, amount: Uint128, cap: Uint128 },
+    /// keeps the amount, fails unless min <= amount <= max
+    Assert { denom: String, min: Uint128, max: Uint128 },
+    /// move amount from one denom to another, e.g. between accounts, fails if from holds less or to would overflow
+    Transfer { from: String, to: String, amount: Uint128 },
+    /// contract is queried with OperationQueryMsg once per distinct current balances,
+    /// a failing query fails its branch, so do the branches beyond MAX_CUSTOM_QUERIES
+    Custom { contract: String, payload: Binary },
+}
+
+/// query sent to the contract of an Operation::Custom
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OperationQueryMsg {
+    #[returns(OperationResp)]
+    ApplyOperation { balances: Balances, payload: Binary },
+}
+
+#[cw_serde]
+pub struct OperationResp {
+    /// None if the operation fails on these balances
+    pub balances: Option<Balances>,
 }
 
 #[cw_serde]
 pub struct Instruction{
     pub tx_id: u32,
     pub commitment: bool,
+    /// coordinator decision log hash after this decision, None from older coordinators
+    #[serde(default)]
+    pub decision_hash: Option<String>,
+    /// set on abort decisions, None from older coordinators or older participants
+    #[serde(default)]
+    pub abort_reason: Option<AbortReason>,
+    /// chains that abort their own part of a committed tx under the coordinator commit policy
+    #[serde(default)]
+    pub aborted_chains: Vec<u16>,
+}
+
+/// decisions of one coordinator flush, applied in order
+#[cw_serde]
+pub struct Instructions{
+    pub items: Vec<Instruction>,
 }
 
 /* Query */
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    /// futures after executing tx_id, paginated by future index,
+    /// mfs older than the last committed tx at a 0.3.0 migration keep the legacy layout and are not served
     #[returns(MultifutureResp)]
-    Multifuture { tx_id: u32 },
+    Multifuture { tx_id: u32, start_after: Option<u32>, limit: Option<u32> },
     #[returns(MyLogsResp)]
     MyLogs{},
+    #[returns(PipelineStateResp)]
+    PipelineState {},
+    #[returns(TxStatusResp)]
+    TxStatus { tx_id: u32 },
+    /// value after the last tx before which everything is finalized
+    #[returns(CommittedValueResp)]
+    CommittedValue {},
+    /// min and max amount of denom across the current futures
+    #[returns(ValueRangeResp)]
+    ValueRange { denom: String },
+    /// value if all pending txs commit or all abort
+    #[returns(PendingOutcomesResp)]
+    PendingOutcomes {},
+    /// dry-run a tx against the current futures without writing state
+    #[returns(SimulateResp)]
+    Simulate { fcross_tx: FcrossTx },
+    #[returns(PauseStateResp)]
+    PauseState {},
+    /// withdrawable is reported for the account of address
+    #[returns(EscrowResp)]
+    Escrow { address: Option<String> },
+    /// digest a vote for fcross_tx carries, to register the tx at the coordinator
+    #[returns(TxDigestResp)]
+    TxDigest { fcross_tx: FcrossTx },
+    /// cw20 compatible, the escrow denom amount of the account every pending outcome leaves at least
+    #[returns(BalanceResponse)]
+    Balance { address: String },
+    /// cw20 compatible, total_supply is the total of the escrow denom accounts every pending outcome leaves at least
+    #[returns(TokenInfoResponse)]
+    TokenInfo {},
 }
 
 #[cw_serde]
 pub struct MultifutureResp {
-    pub futures: Vec<String>,
+    pub tx_id: u32,
+    /// pending txs labelling the futures, the i-th char of a label is the outcome of pending_tx_list[i]
+    pub pending_tx_list: Vec<u32>,
+    /// number of futures in total
+    pub total: u32,
+    pub futures: Vec<FutureEntry>,
+}
+
+#[cw_serde]
+pub struct FutureEntry {
+    pub index: u32,
+    /// '1' for commit and '0' for abort of each pending tx
+    pub label: String,
+    /// amount per denom, None if the branch fails
+    pub value: Option<Balances>,
 }
 
 #[cw_serde]
@@ -52,10 +213,154 @@ pub struct MyLogsResp {
     pub logs: String,
 }
 
+#[cw_serde]
+pub struct PipelineStateResp {
+    pub chain_id: u16,
+    pub expected_tx_id: u32,
+    pub pending_tx_list: Vec<u32>,
+    /// (tx_id, voted) for every pending tx
+    pub pending_votes: Vec<(u32, bool)>,
+    /// window currently used by pending txs, compared against max_pending_len
+    pub pending_len: u32,
+    pub max_pending_len: u32,
+    pub channel: Option<ChannelInfo>,
+    pub coordinator_port: Option<String>,
+    pub rebind_approved: bool,
+}
+
+#[cw_serde]
+pub struct TxStatusResp {
+    pub tx_id: u32,
+    pub executed: bool,
+    pub voted: bool,
+    pub finalized: bool,
+    /// vote sent to the coordinator, true for success
+    pub vote: Option<bool>,
+    /// why the branches of this chain fail, sent with a failure vote
+    pub failure_reason: Option<AbortReason>,
+    /// decision received from the coordinator, true for commit
+    pub decision: Option<bool>,
+    /// coordinator decision log hash received with the decision
+    pub decision_hash: Option<String>,
+    /// abort reason received with the decision
+    pub abort_reason: Option<AbortReason>,
+}
+
+#[cw_serde]
+pub struct CommittedValueResp {
+    pub tx_id: u32,
+    pub value: Option<Balances>,
+}
+
+#[cw_serde]
+pub struct ValueRangeResp {
+    /// latest executed tx
+    pub tx_id: u32,
+    pub denom: String,
+    /// None if every future fails
+    pub min: Option<Uint128>,
+    pub max: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct PendingOutcomesResp {
+    /// latest executed tx
+    pub tx_id: u32,
+    pub pending_tx_list: Vec<u32>,
+    pub all_commit: Option<Balances>,
+    pub all_abort: Option<Balances>,
+}
+
+#[cw_serde]
+pub struct SimulateResp {
+    pub expected_tx_id: u32,
+    /// whether fcross_tx.tx_id would pass the execution order check
+    pub tx_id_matches: bool,
+    pub status: ExecutionStatus,
+    /// futures if the tx commits, in the same order as the current futures
+    pub futures: Vec<Option<Balances>>,
+    /// whether the pending window is full and the tx would be rejected
+    pub window_overflow: bool,
+}
+
+#[cw_serde]
+pub struct PauseStateResp {
+    pub paused: bool,
+    pub admin: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct TxDigestResp {
+    pub digest: String,
+}
+
+#[cw_serde]
+pub struct EscrowResp {
+    pub denom: Option<String>,
+    /// escrow denom coins held by the contract
+    pub held: Uint128,
+    /// lowest amount of the account across the current futures capped by held,
+    /// None without an address or if every future fails
+    pub withdrawable: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct BalanceResponse {
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct TokenInfoResponse {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: Uint128,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum ExecutionStatus {
+    Success,
+    Failure,
+    Uncertainty,
+}
+
+/// why a tx aborts, carried by failure votes and abort decisions
+#[cw_serde]
+#[derive(Copy)]
+pub enum AbortReason {
+    /// a debit exceeds the balance
+    InsufficientBalance,
+    /// a credit exceeds the maximum amount
+    Overflow,
+    /// a compare, floor, cap or range condition of the operation does not hold
+    PreconditionFailed,
+    /// the custom operation contract returned no balances
+    CustomRejected,
+    /// the branch had already failed before the tx
+    PriorFailure,
+    /// a vote digest differs from the registered one
+    DigestMismatch,
+    /// an admin forced the abort
+    Forced,
+    /// the submitter or an admin requested the abort while the vote was open
+    Requested,
+}
+
 #[cw_serde]
 pub struct Vote{
     pub tx_id: u32,
     pub chain_id: u16,
     pub success: bool,
+    /// digest of the executed FcrossTx, see QueryMsg::TxDigest
+    pub digest: Option<String>,
+    /// set on failure votes
+    pub abort_reason: Option<AbortReason>,
+}
+
+/// votes of ExecuteBatch sent as a single packet
+#[cw_serde]
+pub struct Votes{
+    pub items: Vec<Vote>,
 }
 
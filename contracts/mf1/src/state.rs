@@ -1,25 +1,69 @@
 use cosmwasm_schema::cw_serde;
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Empty, Uint128};
 use cw_storage_plus::{Item, Map};
 
+use crate::msg::{AbortReason, Instruction};
+
 pub const CHAIN_ID: Item<u16> = Item::new("chain_id");
 
+// operator controls
+pub const ADMIN: Item<Addr> = Item::new("admin");
+pub const PAUSED: Item<bool> = Item::new("paused"); // stops intake of new txs, instructions are still applied
+
 // mainly use PENDING_TX_LIST, assisted with EXPECTED_TX_ID
 pub const PENDING_TX_LIST: Item<Vec<u32>> = Item::new("pending_tx_list"); // pending changes when instruction comes
 pub const EXPECTED_TX_ID: Item<u32> = Item::new("expected_tx_id");
 
-pub const MF_MAP: Map<u32, Vec<Option<i64>>> = Map::new("mf_maps");
+/// amount per denom, denoms holding zero are left out
+pub type Balances = BTreeMap<String, Uint128>;
+
+pub const MF_MAP: Map<u32, Vec<Option<Balances>>> = Map::new("mf_maps");
 pub const MF_VOTE_MAP: Map<u32, bool> = Map::new("mf_vote_maps"); // only used to record if the mf has voted
+pub const MF_SENT_VOTE_MAP: Map<u32, bool> = Map::new("mf_sent_vote_maps"); // the vote sent for a tx, true for success
+pub const MF_DECISION_MAP: Map<u32, bool> = Map::new("mf_decision_maps"); // the decision received for a tx, true for commit
+pub const MF_EARLY_DECISION_MAP: Map<u32, Instruction> = Map::new("mf_early_decision_maps"); // decisions received before the tx executed here, applied when it does
+pub const MF_VOTE_ACK_MAP: Map<u32, bool> = Map::new("mf_vote_ack_maps"); // whether the coordinator acknowledged the vote
+pub const MF_DECISION_HASH_MAP: Map<u32, String> = Map::new("mf_decision_hash_maps"); // coordinator decision log hash received with the decision
+pub const MF_FAILURE_REASON_MAP: Map<u32, AbortReason> = Map::new("mf_failure_reason_maps"); // why the tx fails in some branch, sent with a failure vote
+pub const MF_ABORT_REASON_MAP: Map<u32, AbortReason> = Map::new("mf_abort_reason_maps"); // abort reason received with the decision
+pub const MF_DIGEST_MAP: Map<u32, String> = Map::new("mf_digest_maps"); // digest of the executed tx, sent with its vote
 
 pub const MAX_PENDING_LEN: u32 = 12;
+pub const MAX_CUSTOM_QUERIES: usize = 64; // distinct balances a custom contract is asked about per tx, the other branches fail
+
+// contracts told about finalizations, per tx or for every tx
+pub const TX_CALLBACK_MAP: Map<u32, Addr> = Map::new("tx_callback_maps");
+pub const SUBSCRIBERS: Map<&Addr, Empty> = Map::new("subscribers");
+pub const MAX_SUBSCRIBERS: u32 = 16;
+pub const CALLBACK_REPLY_ID: u64 = 1;
+pub const CALLBACK_GAS_LIMIT: u64 = 500_000; // an out of gas callback is not caught by reply_on_error
+
+// native coins backing the ledger, deposits and withdrawals only touch the latest mf
+pub const ESCROW_DENOM: Item<String> = Item::new("escrow_denom");
+pub const TOKEN_INFO: Item<TokenMeta> = Item::new("token_info"); // ledger metadata reported by TokenInfo
+
+#[cw_serde]
+pub struct TokenMeta {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
 
 // ibc relevant state
 pub const MY_CHANNEL: Item<ChannelInfo> = Item::new("my_channel");
+pub const COORDINATOR_PORT: Item<String> = Item::new("coordinator_port"); // only channels from this port are accepted
+pub const REBIND_APPROVED: Item<bool> = Item::new("rebind_approved"); // admin allowed MY_CHANNEL to be replaced by a new handshake
 
 #[cw_serde]
 pub struct ChannelInfo {
     pub channel_id: String,
     /// whether the channel is completely set up
     pub finalized: bool,
+    /// whether the channel has been closed, votes are kept until a new channel is bound
+    #[serde(default)]
+    pub closed: bool,
 }
 
 pub const MY_LOGS: Item<String> = Item::new("my_logs");
\ No newline at end of file
@@ -0,0 +1,611 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_ibc_channel_connect_ack, mock_ibc_channel_open_init, mock_ibc_channel_open_try, mock_ibc_packet_ack, mock_ibc_packet_recv, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{coin, from_json, BankMsg, Binary, Coin, CosmosMsg, IbcAcknowledgement, IbcMsg, IbcOrder, OwnedDeps, Uint128};
+use cw_storage_plus::Map;
+
+use crate::contract::exec;
+use crate::error::ContractError;
+use crate::ibc::IBC_APP_VERSION;
+use crate::msg::{AbortReason, BalanceResponse, CommittedValueResp, ExecuteBatchResp, ExecuteMsg, ExecuteTxResp, ExecutionStatus, FcrossTx, InstantiateMsg, Instruction, MigrateMsg, MultifutureResp, Operation, PendingOutcomesResp, QueryMsg, SimulateResp, TokenInfoResponse, ValueRangeResp, Vote, Votes};
+use crate::state::{Balances, ChannelInfo, CALLBACK_GAS_LIMIT, EXPECTED_TX_ID, MAX_PENDING_LEN, MF_DECISION_HASH_MAP, MF_MAP, MF_SENT_VOTE_MAP, MF_VOTE_ACK_MAP, MY_CHANNEL, PENDING_TX_LIST};
+
+type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+const DENOM: &str = "stake";
+
+fn setup(original: u128) -> Deps {
+    let mut deps = mock_dependencies();
+    crate::instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), InstantiateMsg{
+        chain_id: 1,
+        original_balances: vec![coin(original, DENOM)],
+        admin: None,
+        coordinator_port: None,
+        escrow_denom: None,
+        token: None,
+    }).unwrap();
+    MY_CHANNEL.save(deps.as_mut().storage, &ChannelInfo{
+        channel_id: "channel-0".to_string(),
+        finalized: true,
+        closed: false,
+    }).unwrap();
+    deps
+}
+
+fn execute_tx(deps: &mut Deps, tx_id: u32, operation: Operation) -> Vec<Vote> {
+    let resp = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id, operation, callback: None },
+    }).unwrap();
+    votes(resp.messages.into_iter().map(|m| m.msg).collect())
+}
+
+fn finalize(deps: &mut Deps, tx_id: u32, commitment: bool) -> Vec<Vote> {
+    let (_, msgs) = exec::finalize_tx(&mut deps.as_mut(), &mock_env(), &Instruction{
+        tx_id,
+        commitment,
+        decision_hash: None,
+        abort_reason: None,
+        aborted_chains: Vec::new(),
+    }).unwrap();
+    votes(msgs.into_iter().map(|m| m.msg).collect())
+}
+
+fn votes(msgs: Vec<CosmosMsg>) -> Vec<Vote> {
+    msgs.into_iter().filter_map(|msg| match msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket{ data, .. }) => Some(from_json(&data).unwrap()),
+        _ => None,
+    }).collect()
+}
+
+fn debit(amount: u128) -> Operation {
+    Operation::DebitBalance { denom: DENOM.to_string(), amount: Uint128::new(amount) }
+}
+
+fn credit(amount: u128) -> Operation {
+    Operation::CreditBalance { denom: DENOM.to_string(), amount: Uint128::new(amount) }
+}
+
+fn amounts(deps: &Deps, tx_id: u32) -> Vec<Option<u128>> {
+    MF_MAP.load(deps.as_ref().storage, tx_id).unwrap()
+    .into_iter()
+    .map(|v| v.map(|b: Balances| b.get(DENOM).map_or(0, |a| a.u128())))
+    .collect()
+}
+
+#[test]
+fn finalize_prunes_on_the_finalized_tx_bit() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, debit(15));
+    execute_tx(&mut deps, 3, credit(1));
+    // bit 0 is tx 1, bit 1 is tx 2, bit 2 is tx 3
+    assert_eq!(amounts(&deps, 3), vec![
+        Some(20), Some(13), Some(5), None,
+        Some(21), Some(14), Some(6), None,
+    ]);
+
+    finalize(&mut deps, 1, true);
+    assert_eq!(amounts(&deps, 1), vec![Some(13)]);
+    assert_eq!(amounts(&deps, 2), vec![Some(13), None]);
+    assert_eq!(amounts(&deps, 3), vec![Some(13), None, Some(14), None]);
+
+    // tx 2 is now bit 0 of every later mf
+    finalize(&mut deps, 2, false);
+    assert_eq!(amounts(&deps, 3), vec![Some(13), Some(14)]);
+}
+
+#[test]
+fn finalize_prunes_a_middle_tx() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, debit(15));
+    execute_tx(&mut deps, 3, credit(1));
+
+    // tx 2 keeps bit 1 in the mfs of tx 2 and tx 3
+    finalize(&mut deps, 2, false);
+    assert_eq!(amounts(&deps, 2), vec![Some(20), Some(13)]);
+    assert_eq!(amounts(&deps, 3), vec![Some(20), Some(13), Some(21), Some(14)]);
+}
+
+#[test]
+fn uncertain_tx_votes_once_earlier_txs_finalize() {
+    let mut deps = setup(20);
+    assert_eq!(execute_tx(&mut deps, 1, debit(7)).len(), 1);
+    // fails only if tx 1 commits
+    assert!(execute_tx(&mut deps, 2, debit(15)).is_empty());
+    // inherits the failed branch of tx 2
+    assert!(execute_tx(&mut deps, 3, credit(1)).is_empty());
+
+    let votes = finalize(&mut deps, 1, true);
+    assert_eq!(votes.len(), 1);
+    assert_eq!((votes[0].tx_id, votes[0].success), (2, false));
+
+    // tx 3 only counts the branches where it commits itself
+    let votes = finalize(&mut deps, 2, false);
+    assert_eq!(votes.len(), 1);
+    assert_eq!((votes[0].tx_id, votes[0].success), (3, true));
+}
+
+#[test]
+fn finalized_tx_does_not_vote_again() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, debit(15));
+    // tx 2 is finalized before its vote could be given
+    assert!(finalize(&mut deps, 2, false).is_empty());
+    assert_eq!(amounts(&deps, 2), vec![Some(20), Some(13)]);
+    // finalizing the earlier tx afterwards must not vote for tx 2
+    assert!(finalize(&mut deps, 1, true).is_empty());
+    assert_eq!(amounts(&deps, 2), vec![Some(13)]);
+    assert!(MF_SENT_VOTE_MAP.may_load(deps.as_ref().storage, 2).unwrap().is_none());
+}
+
+#[test]
+fn paused_execute_tx_reports_rejection() {
+    let mut deps = setup(20);
+    crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), ExecuteMsg::Pause {}).unwrap();
+    let resp = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id: 1, operation: debit(7), callback: None },
+    }).unwrap();
+    let data: ExecuteTxResp = from_json(resp.data.unwrap()).unwrap();
+    assert_eq!((data.tx_id, data.status, data.rejected), (1, None, true));
+    assert!(MF_MAP.may_load(deps.as_ref().storage, 1).unwrap().is_none());
+
+    crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), ExecuteMsg::Resume {}).unwrap();
+    let resp = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id: 1, operation: debit(7), callback: None },
+    }).unwrap();
+    let data: ExecuteTxResp = from_json(resp.data.unwrap()).unwrap();
+    assert_eq!((data.status, data.rejected), (Some(ExecutionStatus::Success), false));
+}
+
+fn setup_escrow(funds: &[Coin]) -> Result<Deps, ContractError> {
+    let mut deps = mock_dependencies();
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, funds.to_vec());
+    crate::instantiate(deps.as_mut(), mock_env(), mock_info("admin", funds), InstantiateMsg{
+        chain_id: 1,
+        original_balances: vec![coin(20, DENOM)],
+        admin: None,
+        coordinator_port: None,
+        escrow_denom: Some(DENOM.to_string()),
+        token: None,
+    })?;
+    Ok(deps)
+}
+
+#[test]
+fn escrow_instantiate_needs_the_original_balance() {
+    assert!(matches!(setup_escrow(&[]), Err(ContractError::EscrowFundsMismatch { .. })));
+    assert!(matches!(setup_escrow(&[coin(19, DENOM)]), Err(ContractError::EscrowFundsMismatch { .. })));
+    assert!(setup_escrow(&[coin(20, DENOM)]).is_ok());
+}
+
+fn account(address: &str) -> String {
+    format!("{}@{}", address, DENOM)
+}
+
+fn account_amounts(deps: &Deps, address: &str) -> Vec<Option<u128>> {
+    let head = EXPECTED_TX_ID.load(deps.as_ref().storage).unwrap() - 1;
+    MF_MAP.load(deps.as_ref().storage, head).unwrap()
+    .into_iter()
+    .map(|v| v.map(|b: Balances| b.get(&account(address)).map_or(0, |a| a.u128())))
+    .collect()
+}
+
+fn withdraw(deps: &mut Deps, sender: &str, amount: u128) -> Result<cosmwasm_std::Response, ContractError> {
+    crate::execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), ExecuteMsg::Withdraw {
+        amount: Uint128::new(amount),
+        recipient: None,
+    })
+}
+
+#[test]
+fn deposits_and_withdrawals_use_the_account_of_the_sender() {
+    let mut deps = setup_escrow(&[coin(20, DENOM)]).unwrap();
+    crate::execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(5, DENOM)]), ExecuteMsg::Deposit {}).unwrap();
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![coin(25, DENOM)]);
+    assert_eq!(account_amounts(&deps, "alice"), vec![Some(5)]);
+
+    // a pending debit of the account leaves 2 certain
+    execute_tx(&mut deps, 1, Operation::DebitBalance { denom: account("alice"), amount: Uint128::new(3) });
+    assert_eq!(account_amounts(&deps, "alice"), vec![Some(5), Some(2)]);
+    assert!(matches!(withdraw(&mut deps, "bob", 1), Err(ContractError::InsufficientCertainBalance { .. })));
+    assert!(matches!(withdraw(&mut deps, "alice", 3), Err(ContractError::InsufficientCertainBalance { .. })));
+    // the pool is not an account
+    assert!(matches!(withdraw(&mut deps, "admin", 1), Err(ContractError::InsufficientCertainBalance { .. })));
+
+    let resp = withdraw(&mut deps, "alice", 2).unwrap();
+    assert_eq!(resp.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "alice".to_string(), amount: vec![coin(2, DENOM)] }));
+    assert_eq!(account_amounts(&deps, "alice"), vec![Some(3), Some(0)]);
+}
+
+#[test]
+fn payout_is_capped_by_the_escrow_held() {
+    let mut deps = setup_escrow(&[coin(20, DENOM)]).unwrap();
+    // a committed credit from another chain is not backed by coins here
+    execute_tx(&mut deps, 1, Operation::CreditBalance { denom: account("alice"), amount: Uint128::new(30) });
+    finalize(&mut deps, 1, true);
+
+    assert!(matches!(withdraw(&mut deps, "alice", 21), Err(ContractError::InsufficientEscrow { .. })));
+    assert!(withdraw(&mut deps, "alice", 20).is_ok());
+}
+
+fn query<T: serde::de::DeserializeOwned>(deps: &Deps, msg: QueryMsg) -> T {
+    from_json(crate::query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+fn balance(deps: &Deps, address: &str) -> u128 {
+    let resp: BalanceResponse = query(deps, QueryMsg::Balance { address: address.to_string() });
+    resp.balance.u128()
+}
+
+#[test]
+fn transfer_moves_only_the_certain_balance() {
+    let mut deps = setup_escrow(&[coin(20, DENOM)]).unwrap();
+    crate::execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(10, DENOM)]), ExecuteMsg::Deposit {}).unwrap();
+    // pending debit of 4 from alice
+    execute_tx(&mut deps, 1, Operation::DebitBalance { denom: account("alice"), amount: Uint128::new(4) });
+    assert_eq!(balance(&deps, "alice"), 6);
+
+    let transfer = |deps: &mut Deps, amount: u128| crate::execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::Transfer {
+        recipient: "bob".to_string(),
+        amount: Uint128::new(amount),
+    });
+    assert!(matches!(transfer(&mut deps, 7), Err(ContractError::InsufficientCertainBalance { .. })));
+    transfer(&mut deps, 6).unwrap();
+    assert_eq!(account_amounts(&deps, "alice"), vec![Some(4), Some(0)]);
+    assert_eq!(account_amounts(&deps, "bob"), vec![Some(6), Some(6)]);
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (0, 6));
+    let info: TokenInfoResponse = query(&deps, QueryMsg::TokenInfo {});
+    assert_eq!(info.total_supply.u128(), 6);
+
+    // the pending debit aborts and alice keeps the rest
+    finalize(&mut deps, 1, false);
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (4, 6));
+    let info: TokenInfoResponse = query(&deps, QueryMsg::TokenInfo {});
+    assert_eq!(info.total_supply.u128(), 10);
+}
+
+#[test]
+fn transfer_operation_fails_its_branch() {
+    let mut deps = setup(20);
+    let transfer = |amount: u128| Operation::Transfer { from: DENOM.to_string(), to: "other".to_string(), amount: Uint128::new(amount) };
+    execute_tx(&mut deps, 1, transfer(15));
+    // fails only if tx 1 commits
+    assert!(execute_tx(&mut deps, 2, transfer(10)).is_empty());
+    assert_eq!(amounts(&deps, 2), vec![Some(20), Some(5), Some(10), None]);
+
+    let votes = finalize(&mut deps, 1, true);
+    assert_eq!((votes[0].tx_id, votes[0].success, votes[0].abort_reason), (2, false, Some(AbortReason::InsufficientBalance)));
+}
+
+#[test]
+fn contracts_subscribe_themselves_and_callbacks_are_gas_limited() {
+    let mut deps = setup(20);
+    let subscribe = |deps: &mut Deps, sender: &str, contract: &str| crate::execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), ExecuteMsg::Subscribe {
+        contract: contract.to_string(),
+    });
+    assert!(matches!(subscribe(&mut deps, "app", "other"), Err(ContractError::Unauthorized { .. })));
+    subscribe(&mut deps, "app", "app").unwrap();
+    // only the admin manages other subscribers, e.g. to free a slot
+    subscribe(&mut deps, "admin", "other").unwrap();
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("app", &[]), ExecuteMsg::Unsubscribe { contract: "other".to_string() }),
+        Err(ContractError::Unauthorized { .. })
+    ));
+    crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), ExecuteMsg::Unsubscribe { contract: "other".to_string() }).unwrap();
+
+    execute_tx(&mut deps, 1, debit(7));
+    let (_, msgs) = exec::finalize_tx(&mut deps.as_mut(), &mock_env(), &Instruction{
+        tx_id: 1,
+        commitment: true,
+        decision_hash: None,
+        abort_reason: None,
+        aborted_chains: Vec::new(),
+    }).unwrap();
+    let callbacks = msgs.iter().filter(|m| matches!(m.msg, CosmosMsg::Wasm(_))).collect::<Vec<_>>();
+    assert_eq!(callbacks.len(), 1);
+    assert_eq!(callbacks[0].gas_limit, Some(CALLBACK_GAS_LIMIT));
+
+    // the subscriber may leave on its own
+    crate::execute(deps.as_mut(), mock_env(), mock_info("app", &[]), ExecuteMsg::Unsubscribe {
+        contract: "app".to_string(),
+    }).unwrap();
+}
+
+#[test]
+fn failing_custom_query_fails_the_branch() {
+    let mut deps = setup(20);
+    // the mock querier knows no contracts, every query errors
+    let votes = execute_tx(&mut deps, 1, Operation::Custom { contract: "app".to_string(), payload: Binary::default() });
+    assert_eq!(votes.len(), 1);
+    assert_eq!((votes[0].tx_id, votes[0].success), (1, false));
+    assert_eq!(amounts(&deps, 1), vec![Some(20), None]);
+
+    let resp = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id: 2, operation: Operation::Custom { contract: "Not An Address".to_string(), payload: Binary::default() }, callback: None },
+    });
+    assert!(resp.is_err());
+}
+
+fn legacy_v0_2(committed: i64) -> Deps {
+    let mut deps = mock_dependencies();
+    let storage = deps.as_mut().storage;
+    cw2::set_contract_version(storage, "crates.io:mf1", "0.2.0").unwrap();
+    let legacy: Map<u32, Vec<Option<i64>>> = Map::new("mf_maps");
+    // tx 1 and 2 are finalized, tx 3 is pending
+    legacy.save(storage, 1, &vec![Some(-4)]).unwrap();
+    legacy.save(storage, 2, &vec![Some(committed)]).unwrap();
+    legacy.save(storage, 3, &vec![Some(committed), Some(committed - 10)]).unwrap();
+    PENDING_TX_LIST.save(storage, &vec![3]).unwrap();
+    EXPECTED_TX_ID.save(storage, &4).unwrap();
+    deps
+}
+
+fn migrate(deps: &mut Deps) -> Result<cosmwasm_std::Response, ContractError> {
+    crate::migrate(deps.as_mut(), mock_env(), MigrateMsg{ admin: None, legacy_denom: Some(DENOM.to_string()) })
+}
+
+#[test]
+fn migration_only_rewrites_the_committed_and_later_mfs() {
+    let mut deps = legacy_v0_2(8);
+    migrate(&mut deps).unwrap();
+    assert_eq!(amounts(&deps, 2), vec![Some(8)]);
+    // a negative pending branch fails
+    assert_eq!(amounts(&deps, 3), vec![Some(8), None]);
+    // older mfs are left in the legacy layout
+    assert!(MF_MAP.load(deps.as_ref().storage, 1).is_err());
+}
+
+#[test]
+fn migration_fails_on_a_negative_committed_value() {
+    let mut deps = legacy_v0_2(-1);
+    assert!(matches!(migrate(&mut deps), Err(ContractError::NegativeCommittedValue { tx_id: 2, value: -1 })));
+}
+
+#[test]
+fn decision_for_an_unexecuted_tx_applies_at_execution() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    // the coordinator decided tx 2 before it executed here
+    assert!(finalize(&mut deps, 2, false).is_empty());
+    assert!(execute_tx(&mut deps, 2, debit(5)).is_empty());
+    assert_eq!(amounts(&deps, 2), vec![Some(20), Some(13)]);
+    assert_eq!(PENDING_TX_LIST.load(deps.as_ref().storage).unwrap(), vec![1]);
+}
+
+#[test]
+fn decision_hash_is_only_taken_from_ibc() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, debit(1));
+    let instruction = |tx_id: u32| Instruction{
+        tx_id,
+        commitment: true,
+        decision_hash: Some("ab".repeat(32)),
+        abort_reason: None,
+        aborted_chains: Vec::new(),
+    };
+    let finalize_msg = |tx_id: u32| ExecuteMsg::FinalizeTx { instruction: instruction(tx_id) };
+    assert!(matches!(
+        crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), finalize_msg(1)),
+        Err(ContractError::Unauthorized { .. })
+    ));
+    crate::execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), finalize_msg(1)).unwrap();
+    assert!(!MF_DECISION_HASH_MAP.has(deps.as_ref().storage, 1));
+
+    let packet = mock_ibc_packet_recv("channel-0", &instruction(2)).unwrap();
+    crate::ibc::ibc_packet_receive(deps.as_mut(), mock_env(), packet).unwrap();
+    assert!(MF_DECISION_HASH_MAP.has(deps.as_ref().storage, 2));
+}
+
+#[test]
+fn batch_sends_the_immediate_votes_in_one_packet() {
+    let mut deps = setup(20);
+    let txs = vec![debit(7), credit(1), debit(15)]
+    .into_iter()
+    .enumerate()
+    .map(|(i, operation)| FcrossTx{ tx_id: i as u32 + 1, operation, callback: None })
+    .collect::<Vec<FcrossTx>>();
+    let resp = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteBatch { txs }).unwrap();
+
+    let data: ExecuteBatchResp = from_json(resp.data.unwrap()).unwrap();
+    let statuses = data.items.iter().map(|item| item.status).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![Some(ExecutionStatus::Success), Some(ExecutionStatus::Success), Some(ExecutionStatus::Uncertainty)]);
+
+    // tx 3 fails whenever tx 1 commits, so only tx 1 and 2 vote
+    let packets = resp.messages.iter().filter_map(|m| match &m.msg {
+        CosmosMsg::Ibc(IbcMsg::SendPacket{ data, .. }) => Some(from_json::<Votes>(data).unwrap()),
+        _ => None,
+    }).collect::<Vec<Votes>>();
+    assert_eq!(packets.len(), 1);
+    let voted = packets[0].items.iter().map(|v| (v.tx_id, v.success)).collect::<Vec<_>>();
+    assert_eq!(voted, vec![(1, true), (2, true)]);
+
+    // the ack of the packet acknowledges every vote in it
+    let ack = mock_ibc_packet_ack("channel-0", &packets[0], IbcAcknowledgement::new(b"coordinator_success")).unwrap();
+    crate::ibc::ibc_packet_ack(deps.as_mut(), mock_env(), ack).unwrap();
+    for tx_id in 1..=2 {
+        assert!(MF_VOTE_ACK_MAP.load(deps.as_ref().storage, tx_id).unwrap());
+    }
+    assert!(!MF_VOTE_ACK_MAP.has(deps.as_ref().storage, 3));
+}
+
+// futures of tx 2 after a pending debit of 7 from 20, the first half aborts tx 2
+fn after_pending_debit(operation: Operation) -> (Vec<Option<u128>>, Vec<Vote>) {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    let votes = execute_tx(&mut deps, 2, operation);
+    (amounts(&deps, 2)[2..].to_vec(), votes)
+}
+
+fn amount(amount: u128) -> Uint128 {
+    Uint128::new(amount)
+}
+
+#[test]
+fn conditional_operations_fail_only_their_branches() {
+    let denom = || DENOM.to_string();
+    let (futures, votes) = after_pending_debit(Operation::Set { denom: denom(), value: amount(5) });
+    assert_eq!((futures, votes.len()), (vec![Some(5), Some(5)], 1));
+
+    let (futures, votes) = after_pending_debit(Operation::CompareAndSet { denom: denom(), expected: amount(13), value: amount(1) });
+    assert_eq!((futures, votes.len()), (vec![None, Some(1)], 0));
+
+    let (futures, _) = after_pending_debit(Operation::DebitIfAbove { denom: denom(), amount: amount(5), floor: amount(10) });
+    assert_eq!(futures, vec![Some(15), None]);
+
+    let (futures, _) = after_pending_debit(Operation::CreditCapped { denom: denom(), amount: amount(5), cap: amount(20) });
+    assert_eq!(futures, vec![None, Some(18)]);
+
+    // a failing precondition alone gives a failure vote
+    let (futures, votes) = after_pending_debit(Operation::Assert { denom: denom(), min: amount(21), max: amount(30) });
+    assert_eq!(futures, vec![None, None]);
+    assert_eq!((votes[0].success, votes[0].abort_reason), (false, Some(AbortReason::PreconditionFailed)));
+
+    let (futures, _) = after_pending_debit(Operation::Assert { denom: denom(), min: amount(10), max: amount(15) });
+    assert_eq!(futures, vec![None, Some(13)]);
+}
+
+#[test]
+fn overflow_fails_only_its_branch() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    // fits on 13 but not on 20
+    assert!(execute_tx(&mut deps, 2, credit(u128::MAX - 15)).is_empty());
+    assert_eq!(amounts(&deps, 2)[2..], [None, Some(u128::MAX - 2)]);
+
+    let votes = finalize(&mut deps, 1, false);
+    assert_eq!((votes[0].tx_id, votes[0].success, votes[0].abort_reason), (2, false, Some(AbortReason::Overflow)));
+}
+
+#[test]
+fn malformed_operations_are_rejected() {
+    let mut deps = setup(20);
+    let execute = |deps: &mut Deps, operation: Operation| crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id: 1, operation, callback: None },
+    });
+    assert!(matches!(
+        execute(&mut deps, Operation::Assert { denom: DENOM.to_string(), min: amount(5), max: amount(4) }),
+        Err(ContractError::InvalidRange { .. })
+    ));
+    assert!(matches!(
+        execute(&mut deps, Operation::DebitBalance { denom: String::new(), amount: amount(1) }),
+        Err(ContractError::EmptyDenom {})
+    ));
+    // the tx id is still expected
+    assert_eq!(execute_tx(&mut deps, 1, debit(1)).len(), 1);
+}
+
+#[test]
+fn multifuture_pages_labelled_futures() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, debit(15));
+
+    let resp: MultifutureResp = query(&deps, QueryMsg::Multifuture { tx_id: 2, start_after: Some(0), limit: Some(2) });
+    assert_eq!((resp.pending_tx_list, resp.total), (vec![1, 2], 4));
+    let futures = resp.futures.into_iter()
+    .map(|f| (f.index, f.label, f.value.map(|b| b[DENOM].u128())))
+    .collect::<Vec<_>>();
+    assert_eq!(futures, vec![(1, "10".to_string(), Some(13)), (2, "01".to_string(), Some(5))]);
+
+    // the last page holds the failed branch
+    let resp: MultifutureResp = query(&deps, QueryMsg::Multifuture { tx_id: 2, start_after: Some(2), limit: None });
+    let futures = resp.futures.into_iter().map(|f| (f.index, f.label, f.value)).collect::<Vec<_>>();
+    assert_eq!(futures, vec![(3, "11".to_string(), None)]);
+}
+
+#[test]
+fn value_queries_follow_the_pending_txs() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+    execute_tx(&mut deps, 2, credit(5));
+    let value = |b: Option<Balances>| b.map(|b| b[DENOM].u128());
+    let range = |deps: &Deps| {
+        let resp: ValueRangeResp = query(deps, QueryMsg::ValueRange { denom: DENOM.to_string() });
+        (resp.tx_id, resp.min.map(|a| a.u128()), resp.max.map(|a| a.u128()))
+    };
+
+    let committed: CommittedValueResp = query(&deps, QueryMsg::CommittedValue {});
+    assert_eq!((committed.tx_id, value(committed.value)), (0, Some(20)));
+    assert_eq!(range(&deps), (2, Some(13), Some(25)));
+    let outcomes: PendingOutcomesResp = query(&deps, QueryMsg::PendingOutcomes {});
+    assert_eq!(outcomes.pending_tx_list, vec![1, 2]);
+    assert_eq!((value(outcomes.all_commit), value(outcomes.all_abort)), (Some(18), Some(20)));
+
+    finalize(&mut deps, 1, true);
+    let committed: CommittedValueResp = query(&deps, QueryMsg::CommittedValue {});
+    assert_eq!((committed.tx_id, value(committed.value)), (1, Some(13)));
+    assert_eq!(range(&deps), (2, Some(13), Some(18)));
+    let outcomes: PendingOutcomesResp = query(&deps, QueryMsg::PendingOutcomes {});
+    assert_eq!(outcomes.pending_tx_list, vec![2]);
+    assert_eq!((value(outcomes.all_commit), value(outcomes.all_abort)), (Some(18), Some(13)));
+
+    // a denom the ledger never held counts as zero
+    let resp: ValueRangeResp = query(&deps, QueryMsg::ValueRange { denom: "uatom".to_string() });
+    assert_eq!((resp.min, resp.max), (Some(Uint128::zero()), Some(Uint128::zero())));
+}
+
+fn simulate(deps: &Deps, tx_id: u32, operation: Operation) -> SimulateResp {
+    query(deps, QueryMsg::Simulate { fcross_tx: FcrossTx{ tx_id, operation, callback: None } })
+}
+
+#[test]
+fn simulate_reports_the_tx_without_executing_it() {
+    let mut deps = setup(20);
+    execute_tx(&mut deps, 1, debit(7));
+
+    let resp = simulate(&deps, 2, debit(15));
+    assert_eq!((resp.expected_tx_id, resp.tx_id_matches, resp.status), (2, true, ExecutionStatus::Uncertainty));
+    let futures = resp.futures.into_iter().map(|v| v.map(|b| b[DENOM].u128())).collect::<Vec<_>>();
+    assert_eq!(futures, vec![Some(5), None]);
+    assert!(!resp.window_overflow);
+    assert!(!simulate(&deps, 3, debit(15)).tx_id_matches);
+    assert_eq!(EXPECTED_TX_ID.load(deps.as_ref().storage).unwrap(), 2);
+    assert!(!MF_MAP.has(deps.as_ref().storage, 2));
+    assert_eq!(PENDING_TX_LIST.load(deps.as_ref().storage).unwrap(), vec![1]);
+
+    // the window holds MAX_PENDING_LEN pending txs plus the one being executed
+    for tx_id in 2..=MAX_PENDING_LEN {
+        execute_tx(&mut deps, tx_id, credit(1));
+    }
+    assert!(!simulate(&deps, MAX_PENDING_LEN+1, credit(1)).window_overflow);
+    execute_tx(&mut deps, MAX_PENDING_LEN+1, credit(1));
+    assert!(simulate(&deps, MAX_PENDING_LEN+2, credit(1)).window_overflow);
+    let err = crate::execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::ExecuteTx {
+        fcross_tx: FcrossTx{ tx_id: MAX_PENDING_LEN+2, operation: credit(1), callback: None },
+    }).unwrap_err();
+    assert_eq!(err, ContractError::UpperBound { max_length: MAX_PENDING_LEN });
+}
+
+#[test]
+fn handshake_checks_order_version_and_coordinator_port() {
+    let mut deps = mock_dependencies();
+    crate::instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), InstantiateMsg{
+        chain_id: 1,
+        original_balances: vec![coin(10, DENOM)],
+        admin: None,
+        coordinator_port: Some("their_port".to_string()),
+        escrow_denom: None,
+        token: None,
+    }).unwrap();
+    let open = |deps: &mut Deps, msg| crate::ibc::ibc_channel_open(deps.as_mut(), mock_env(), msg);
+
+    let err = open(&mut deps, mock_ibc_channel_open_init("channel-0", IbcOrder::Ordered, IBC_APP_VERSION)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcOrder {});
+    let err = open(&mut deps, mock_ibc_channel_open_try("channel-0", IbcOrder::Unordered, "myfc-0")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcVersion { version: "myfc-0".to_string(), expected: IBC_APP_VERSION.to_string() });
+    assert!(MY_CHANNEL.may_load(deps.as_ref().storage).unwrap().is_none());
+
+    let resp = open(&mut deps, mock_ibc_channel_open_init("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap();
+    assert_eq!(resp.unwrap().version, IBC_APP_VERSION);
+    let err = crate::ibc::ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_ack("channel-0", IbcOrder::Unordered, "myfc-0")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidIbcVersion { version: "myfc-0".to_string(), expected: IBC_APP_VERSION.to_string() });
+    crate::ibc::ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_ack("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap();
+    assert!(MY_CHANNEL.load(deps.as_ref().storage).unwrap().finalized);
+
+    // without a configured port no coordinator is trusted
+    let mut deps = setup(10);
+    MY_CHANNEL.remove(deps.as_mut().storage);
+    let err = open(&mut deps, mock_ibc_channel_open_init("channel-0", IbcOrder::Unordered, IBC_APP_VERSION)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidCounterpartyPort { port_id: "their_port".to_string() });
+}
@@ -1,24 +1,98 @@
-use cosmwasm_std::{Deps, StdResult, Env, IbcTimeout};
+use cosmwasm_std::{Addr, Deps, Event, StdError, StdResult, Env, IbcTimeout, Uint128};
+use semver::Version;
+use sha2::{Digest, Sha256};
 
-use crate::state::{EXPECTED_TX_ID, PENDING_TX_LIST};
+use crate::error::ContractError;
+use crate::msg::FcrossTx;
 
-pub fn future_index_to_string(index: u16) -> String {
-    format!("{:b}", index)
+use crate::state::{ADMIN, EXPECTED_TX_ID, PAUSED, PENDING_TX_LIST, Balances, TokenMeta};
+
+// lowest bit first, padded to one char per pending tx
+pub fn future_index_to_string(index: u16, width: usize) -> String {
+    format!("{:0width$b}", index, width = width)
     .chars()
     .rev()
+    .take(width)
     .collect()
 }
 
 pub fn calculate_pending_list_len(deps: Deps) -> StdResult<u32> {
     let list = PENDING_TX_LIST.load(deps.storage)?;
-    if list.len()==0 {
+    if list.is_empty() {
         Ok(0)
     } else {
         Ok(EXPECTED_TX_ID.load(deps.storage)?-list[0])
     }
 }
 
-pub fn keys_format(ks: &Vec<u32>) -> String {
+// every tx up to the returned one is finalized
+pub fn last_committed_tx_id(deps: Deps) -> StdResult<u32> {
+    let list = PENDING_TX_LIST.load(deps.storage)?;
+    match list.first() {
+        Some(&first) => Ok(first-1),
+        None => Ok(EXPECTED_TX_ID.load(deps.storage)?-1),
+    }
+}
+
+// the amount of denom every future holds at least, only this much can leave the ledger locally
+pub fn certain_value(values: &[Option<Balances>], denom: &str) -> Option<Uint128> {
+    values.iter().flatten().map(|b| amount_of(b, denom)).min()
+}
+
+// the total of the accounts of denom every future holds at least
+pub fn certain_supply(values: &[Option<Balances>], denom: &str) -> Option<Uint128> {
+    let suffix = format!("@{}", denom);
+    values
+    .iter()
+    .flatten()
+    .map(|b| b.iter().filter(|(k, _)| k.ends_with(&suffix)).fold(Uint128::zero(), |total, (_, v)| total.saturating_add(*v)))
+    .min()
+}
+
+pub fn amount_of(balances: &Balances, denom: &str) -> Uint128 {
+    balances.get(denom).copied().unwrap_or_default()
+}
+
+// change a single denom, None if f fails the branch
+pub fn update_denom(balances: &Balances, denom: &str, f: impl FnOnce(Uint128) -> Option<Uint128>) -> Option<Balances> {
+    let amount = f(amount_of(balances, denom))?;
+    let mut balances = balances.clone();
+    if amount.is_zero() {
+        balances.remove(denom);
+    } else {
+        balances.insert(denom.to_string(), amount);
+    }
+    Some(balances)
+}
+
+// ledger entry of an account, '@' is not allowed in denoms so it never clashes with a plain denom
+pub fn account_key(address: &Addr, denom: &str) -> String {
+    format!("{}@{}", address, denom)
+}
+
+pub fn default_token_meta(chain_id: u16) -> TokenMeta {
+    TokenMeta{
+        name: format!("mf1 ledger {}", chain_id),
+        symbol: "MFL".to_string(),
+        decimals: 0,
+    }
+}
+
+// hex sha256 of the json of [tx_id, operation], the callback is local and left out
+pub fn tx_digest(tx: &FcrossTx) -> StdResult<String> {
+    let data = cosmwasm_std::to_json_vec(&(tx.tx_id, &tx.operation))?;
+    Ok(hex::encode(Sha256::digest(data)))
+}
+
+pub fn tx_ids_format(ids: &[u32]) -> String {
+    ids
+    .iter()
+    .map(|i| i.to_string())
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+pub fn keys_format(ks: &[u32]) -> String {
     ks
     .iter()
     .map(|&i| format!("{:b}", i).chars().rev().collect())
@@ -29,4 +103,46 @@ pub fn keys_format(ks: &Vec<u32>) -> String {
 pub fn get_timeout(env: &Env) -> IbcTimeout {
     let timeout = env.block.time.plus_seconds(3600000);
     IbcTimeout::with_timestamp(timeout)
-}
\ No newline at end of file
+}
+
+pub fn ensure_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    match ADMIN.may_load(deps.storage)? {
+        Some(admin) if admin == *sender => Ok(()),
+        _ => Err(ContractError::Unauthorized { sender: sender.clone() }),
+    }
+}
+
+pub fn is_paused(deps: Deps) -> StdResult<bool> {
+    Ok(PAUSED.may_load(deps.storage)?.unwrap_or(false))
+}
+
+// rejected calls succeed without touching state so that the event is kept
+pub fn paused_rejection_event(action: &str) -> Event {
+    Event::new("paused_rejection")
+    .add_attribute("action", action)
+}
+
+// instances deployed before versioning was introduced have no cw2 record
+pub const LEGACY_VERSION: &str = "0.1.0";
+
+// check the stored contract before migrating, returns the previous version
+pub fn check_migration(deps: Deps, name: &str, version: &str) -> Result<Version, ContractError> {
+    let previous_version = match cw2::CONTRACT.may_load(deps.storage)? {
+        Some(previous) => {
+            if previous.contract != name {
+                return Err(ContractError::InvalidMigrationContract { previous_contract: previous.contract })
+            }
+            previous.version
+        },
+        None => LEGACY_VERSION.to_string(),
+    };
+    let previous = parse_version(&previous_version)?;
+    if previous > parse_version(version)? {
+        return Err(ContractError::InvalidMigrationVersion { previous_version, new_version: version.to_string() })
+    }
+    Ok(previous)
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version).map_err(|e| StdError::generic_err(e.to_string()))
+}
@@ -31,8 +31,8 @@ deployTestSmartContract() {
 
 deployMf1() {
   contractName=mf1
-  initMsg='{"chain_id": 0, "original_value": 20}'
-  execMsg='{"execute_tx":{"fcross_tx":{"tx_id":1,"operation":{"debit_balance":{"amount":7}}}}}'
+  initMsg='{"chain_id": 0, "original_balances": [{"denom": "stake", "amount": "20"}]}'
+  execMsg='{"execute_tx":{"fcross_tx":{"tx_id":1,"operation":{"debit_balance":{"denom":"stake","amount":"7"}}}}}'
   queryMsg='{ "all_futures": {} }'
 
   smartContractDir=$SCRIPTDIR/contracts/$contractName
@@ -49,7 +49,8 @@ deployMf1() {
     wasmBinary="$smartContractDir/target/wasm32-unknown-unknown/release/$contractName.wasm"
     wasmd $homeFlag tx wasm store $wasmBinary $rpcFlag --from user --chain-id ibc-$i --gas-prices "0.025stake" --gas "20000000" --broadcast-mode block -y --keyring-backend test
     codeId=$(wasmd $homeFlag query wasm list-code $rpcFlag --output json | jq -r ".code_infos[-1] | .code_id")
-    wasmd $homeFlag tx wasm instantiate $codeId "$initMsg" $rpcFlag --from user --chain-id ibc-$i $gasFlag --broadcast-mode block -y --keyring-backend test --label "hello" --no-admin
+    adminAddr=$(wasmd $homeFlag keys show user -a --keyring-backend test)
+    wasmd $homeFlag tx wasm instantiate $codeId "$initMsg" $rpcFlag --from user --chain-id ibc-$i $gasFlag --broadcast-mode block -y --keyring-backend test --label "hello" --admin $adminAddr
     # query the contract instance address
     contractAddr=$(wasmd $homeFlag query wasm list-contract-by-code $codeId $rpcFlag --output json | jq -r '.contracts[-1]')
     wasmd $homeFlag tx wasm execute $contractAddr "$execMsg" $gasFlag $rpcFlag --chain-id ibc-$i --from user --broadcast-mode block -y --keyring-backend test
@@ -88,25 +89,40 @@ deployMfAndCoordinator() {
       initMsg="{\"chain_num\":$logicChainNum}"
       execMsg='{"add_vote":{"vote":{"tx_id":1,"chain_id":0,"success":true}}}'
       queryMsg='{"opening_votes":{}}'
+      fundsFlag=""
       wasmBinary="$SCRIPTDIR/contracts/$coordinatorName/target/wasm32-unknown-unknown/release/$coordinatorName.wasm"
     else
-      initMsg="{\"chain_id\": $i, \"original_value\": 20}"
-      execMsg='{"execute_tx":{"fcross_tx":{"tx_id":1,"operation":{"debit_balance":{"amount":7}}}}}'
+      initMsg="{\"chain_id\": $i, \"original_balances\": [{\"denom\": \"stake\", \"amount\": \"20\"}], \"coordinator_port\": \"${ibcPorts[0]}\", \"escrow_denom\": \"stake\"}"
+      # the escrow denom original balance is attached as funds
+      fundsFlag="--amount 20stake"
+      execMsg='{"execute_tx":{"fcross_tx":{"tx_id":1,"operation":{"debit_balance":{"denom":"stake","amount":"7"}}}}}'
       queryMsg='{"multifuture":{"tx_id":1}}'
       wasmBinary="$SCRIPTDIR/contracts/$contractName/target/wasm32-unknown-unknown/release/$contractName.wasm"
     fi
     
     wasmd $homeFlag tx wasm store $wasmBinary $rpcFlag --from user --chain-id ibc-$i --gas-prices "0.025stake" --gas "20000000" --broadcast-mode block -y --keyring-backend test
     codeId=$(wasmd $homeFlag query wasm list-code $rpcFlag --output json | jq -r ".code_infos[-1] | .code_id")
-    wasmd $homeFlag tx wasm instantiate $codeId "$initMsg" $rpcFlag --from user --chain-id ibc-$i $gasFlag --broadcast-mode block -y --keyring-backend test --label "hello" --no-admin
+    adminAddr=$(wasmd $homeFlag keys show user -a --keyring-backend test)
+    wasmd $homeFlag tx wasm instantiate $codeId "$initMsg" $rpcFlag --from user --chain-id ibc-$i $gasFlag --broadcast-mode block -y --keyring-backend test --label "hello" --admin $adminAddr $fundsFlag
     # query the contract instance address
     contractAddr=$(wasmd $homeFlag query wasm list-contract-by-code $codeId $rpcFlag --output json | jq -r '.contracts[-1]')
     # query its ibc port
     contractIbcPort=$(wasmd $homeFlag query wasm contract $contractAddr $rpcFlag --output --json | jq -r '.contract_info | .ibc_port_id')
     ibcPorts+=("$contractIbcPort")
+    if (( i == 0 )); then
+      coordinatorAddr=$contractAddr
+    fi
     # wasmd $homeFlag tx wasm execute $contractAddr "$execMsg" $gasFlag $rpcFlag --chain-id ibc-$i --from user --broadcast-mode block -y --keyring-backend test
     # wasmd $homeFlag query wasm contract-state smart $contractAddr "$queryMsg" $rpcFlag
   done
+
+  # the coordinator only accepts channels from participant ports
+  homeFlag="--home $WASMD_DATA/ibc-0"
+  rpcFlag="--node http://127.0.0.1:26550"
+  for((i=1;i<$1;i++)); do
+    allowMsg="{\"allow_port\":{\"port_id\":\"${ibcPorts[i]}\"}}"
+    wasmd $homeFlag tx wasm execute $coordinatorAddr "$allowMsg" $gasFlag $rpcFlag --chain-id ibc-0 --from user --broadcast-mode block -y --keyring-backend test
+  done
 }
 
 # input check
@@ -170,7 +186,7 @@ for ((i=0;i<chainNum;i++)); do
       # create connection between chains
       rly --home $RELAYER_HOME paths new ibc-0 ibc-$i mypath0-$i
       # create channel between contracts
-      rly --home $RELAYER_HOME tx link mypath0-$i --src-port ${ibcPorts[0]} --dst-port ${ibcPorts[i]} --order unordered --version v1
+      rly --home $RELAYER_HOME tx link mypath0-$i --src-port ${ibcPorts[0]} --dst-port ${ibcPorts[i]} --order unordered --version myfc-1
       rly --home $RELAYER_HOME start mypath0-$i --debug-addr localhost:750$i > $RELAYER_LOGS/mypath0-$i.log 2>&1 &
     fi
     # delete user to rename it to ibc-$i, --home and --keyring-backend flags are necessary for wasmd